@@ -1,30 +1,30 @@
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use std::error::Error;
+pub mod cache;
+pub mod error;
+pub mod http;
+pub mod nutritionix;
+pub mod open_food_facts;
+pub mod usda;
 
-#[derive(Serialize)]
-struct RequestBody<'a> {
-    query: &'a str,
-    timezone: &'a str,
-}
+use crate::app::{Gender, Profile, Workout, WorkoutType};
+use crate::config::{Config, Provider};
+use crate::food::Food;
+use crate::products::Product;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug)]
-struct ApiResponse {
-    foods: Vec<FoodItem>,
-}
+pub use cache::NutritionCache;
+pub use error::ApiError;
+pub use http::HttpClient;
+pub use nutritionix::Nutritionix;
+pub use open_food_facts::OpenFoodFacts;
+pub use usda::Usda;
 
-#[derive(Deserialize, Debug)]
-struct FoodItem {
-    food_name: String,
-    serving_qty: f64,
-    serving_unit: String,
-    nf_calories: f64,
-    nf_total_fat: f64,
-    nf_protein: f64,
-    nf_total_carbohydrate: Option<f64>,
+/// Name of the system time zone, such as `Europe/Berlin`, falling back to UTC.
+pub fn local_timezone() -> String {
+    iana_time_zone::get_timezone().unwrap_or_else(|_| "UTC".to_string())
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NutritionInfo {
     pub name: String,
     pub quantity: f64,
@@ -35,54 +35,235 @@ pub struct NutritionInfo {
     pub carbs: f64,
 }
 
+impl NutritionInfo {
+    pub fn to_food(&self) -> Food {
+        Food::new(
+            &self.name,
+            self.quantity,
+            &self.unit,
+            self.protein,
+            self.fat,
+            self.carbs,
+            self.calories,
+        )
+    }
+}
+
+/// An exercise recognized in a description such as "ran 5k in 30 min".
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExerciseInfo {
+    pub name: String,
+    pub duration_min: f64,
+    pub calories: f64,
+}
+
+/// Words in exercise names that mean strength training rather than cardio.
+const STRENGTH_WORDS: [&str; 6] = [
+    "weight",
+    "lifting",
+    "strength",
+    "resistance",
+    "bodybuilding",
+    "calisthenics",
+];
+
+impl ExerciseInfo {
+    pub fn to_workout(&self) -> Workout {
+        let name = self.name.to_lowercase();
+        let workout_type = if STRENGTH_WORDS.iter().any(|word| name.contains(word)) {
+            WorkoutType::WeightLifting
+        } else {
+            WorkoutType::Cardio
+        };
+        Workout {
+            name: Some(self.name.clone()),
+            workout_type,
+            duration: self.duration_min.round() as u32,
+            calories_burnt: self.calories.round() as u32,
+        }
+    }
+}
+
+/// The body measurements exercise estimates depend on.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Person {
+    pub weight_kg: f32,
+    pub height_cm: f32,
+    pub age: u32,
+    pub gender: &'static str,
+}
+
+impl From<&Profile> for Person {
+    fn from(profile: &Profile) -> Self {
+        Self {
+            weight_kg: profile.weight,
+            height_cm: profile.height,
+            age: profile.age,
+            gender: match profile.gender {
+                Gender::Male => "male",
+                Gender::Female => "female",
+            },
+        }
+    }
+}
+
+/// A service that turns a food query into nutrition values.
+#[async_trait]
+pub trait NutritionProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn search(&self, query: &str) -> Result<Vec<NutritionInfo>, ApiError>;
+
+    /// Looks up a packaged product by UPC or EAN code. Providers without
+    /// barcode support find nothing.
+    async fn lookup_barcode(&self, _barcode: &str) -> Result<Option<Product>, ApiError> {
+        Ok(None)
+    }
+
+    /// Food names starting with or resembling `query`, for suggestions while
+    /// typing. Providers without an instant search suggest nothing.
+    async fn suggest(&self, _query: &str) -> Result<Vec<String>, ApiError> {
+        Ok(Vec::new())
+    }
+
+    /// Recognizes exercises described in words and estimates the calories
+    /// `person` burnt. Providers without exercise support return `None`.
+    async fn search_exercise(
+        &self,
+        _query: &str,
+        _person: &Person,
+    ) -> Result<Option<Vec<ExerciseInfo>>, ApiError> {
+        Ok(None)
+    }
+}
+
+/// The configured providers, in the order they should be tried, sharing
+/// `client`. Providers that need credentials are left out when none are set.
+pub fn providers(config: &Config, client: &HttpClient) -> Vec<Box<dyn NutritionProvider>> {
+    config
+        .providers
+        .iter()
+        .filter(|&&provider| config.is_configured(provider))
+        .filter_map(|provider| -> Option<Box<dyn NutritionProvider>> {
+            let base_url = |default| {
+                config
+                    .base_urls
+                    .get(provider)
+                    .map_or(default, String::as_str)
+            };
+            match provider {
+                Provider::Nutritionix => config.nutritionix.clone().map(|credentials| {
+                    Box::new(
+                        Nutritionix::new(credentials, client.clone())
+                            .with_base_url(base_url(Nutritionix::BASE_URL)),
+                    ) as _
+                }),
+                Provider::Usda => config.usda_api_key.clone().map(|api_key| {
+                    Box::new(
+                        Usda::new(api_key, client.clone()).with_base_url(base_url(Usda::BASE_URL)),
+                    ) as _
+                }),
+                Provider::OpenFoodFacts => Some(Box::new(
+                    OpenFoodFacts::new(client.clone())
+                        .with_base_url(base_url(OpenFoodFacts::BASE_URL)),
+                )),
+            }
+        })
+        .collect()
+}
+
+/// Asks each provider in turn until one finds something. A provider that
+/// fails is skipped; if all fail, the last error is returned.
+///
+/// With a cache, results cached for `query` are returned without asking the
+/// providers, new results are cached, and expired results are returned when
+/// every provider fails.
 pub async fn search_and_get_nutrition(
     query: &str,
-) -> Result<Vec<NutritionInfo>, Box<dyn Error + Send + Sync>> {
-    let app_id = "4954cf1b";
-    let api_key = "0b19a47f300a9bfa16649d584df7ee5d";
-    let url = "https://trackapi.nutritionix.com/v2/natural/nutrients";
-
-    let client = Client::new();
-    let request_body = RequestBody {
-        query,
-        timezone: "US/Eastern",
-    };
-
-    let response = client
-        .post(url)
-        .header("x-app-id", app_id)
-        .header("x-app-key", api_key)
-        .json(&request_body)
-        .send()
-        .await?;
-
-    if response.status().is_success() {
-        let response_text = response.text().await?;
-
-        let api_response: ApiResponse = serde_json::from_str(&response_text)?;
-
-        let nutrition_info = api_response
-            .foods
-            .into_iter()
-            .map(|food| {
-                let carbs = food.nf_total_carbohydrate.unwrap_or_else(|| {
-                    (food.nf_calories - (food.nf_protein * 4.0) - (food.nf_total_fat * 9.0)) / 4.0
-                });
-
-                NutritionInfo {
-                    name: food.food_name,
-                    quantity: food.serving_qty,
-                    unit: food.serving_unit,
-                    calories: food.nf_calories,
-                    protein: food.nf_protein,
-                    fat: food.nf_total_fat,
-                    carbs: (carbs * 10.0).round() / 10.0,
+    providers: &[Box<dyn NutritionProvider>],
+    cache: Option<&NutritionCache>,
+) -> Result<Vec<NutritionInfo>, ApiError> {
+    if let Some((items, _)) = cache.and_then(|cache| cache.get(query)) {
+        return Ok(items);
+    }
+    let mut result: Result<Vec<NutritionInfo>, ApiError> = Err(ApiError::NotConfigured(None));
+    for provider in providers {
+        match provider.search(query).await {
+            Ok(info) if !info.is_empty() => {
+                if let Some(cache) = cache {
+                    // A cache that cannot be written only costs a repeated request.
+                    let _ = cache.insert(query, provider.name(), &info);
                 }
-            })
-            .collect();
+                return Ok(info);
+            }
+            Ok(info) => result = Ok(info),
+            Err(e) if result.is_err() => result = Err(e.from_provider(provider.name())),
+            Err(_) => {}
+        }
+    }
+    match cache.and_then(|cache| cache.get_stale(query)) {
+        Some((items, _)) if result.is_err() => Ok(items),
+        _ => result,
+    }
+}
+
+/// Asks each provider in turn for the product with `barcode`. Providers that
+/// fail are skipped; if all fail, the last error is returned.
+pub async fn lookup_barcode(
+    barcode: &str,
+    providers: &[Box<dyn NutritionProvider>],
+) -> Result<Option<(Product, &'static str)>, ApiError> {
+    let mut result: Result<Option<(Product, &'static str)>, ApiError> =
+        Err(ApiError::NotConfigured(None));
+    for provider in providers {
+        match provider.lookup_barcode(barcode).await {
+            Ok(Some(product)) => return Ok(Some((product, provider.name()))),
+            Ok(None) => result = Ok(None),
+            Err(e) if result.is_err() => result = Err(e.from_provider(provider.name())),
+            Err(_) => {}
+        }
+    }
+    result
+}
 
-        Ok(nutrition_info)
-    } else {
-        Err(format!("API request failed: {}", response.status()).into())
+/// Food names suggested for `query` by the first provider that has any.
+pub async fn suggest(
+    query: &str,
+    providers: &[Box<dyn NutritionProvider>],
+) -> Result<Vec<String>, ApiError> {
+    let mut result: Result<Vec<String>, ApiError> = Err(ApiError::NotConfigured(None));
+    for provider in providers {
+        match provider.suggest(query).await {
+            Ok(names) if !names.is_empty() => return Ok(names),
+            Ok(names) => result = Ok(names),
+            Err(e) if result.is_err() => result = Err(e.from_provider(provider.name())),
+            Err(_) => {}
+        }
     }
+    result
+}
+
+/// Asks the providers that support exercise, in turn, for the exercises
+/// described in `query`. Fails with `ApiError::NotConfigured` when none does.
+pub async fn search_exercise(
+    query: &str,
+    profile: &Profile,
+    providers: &[Box<dyn NutritionProvider>],
+) -> Result<Vec<ExerciseInfo>, ApiError> {
+    let person = Person::from(profile);
+    let mut result: Result<Vec<ExerciseInfo>, ApiError> = Err(ApiError::NotConfigured(None));
+    for provider in providers {
+        match provider.search_exercise(query, &person).await {
+            Ok(Some(exercises)) if !exercises.is_empty() => return Ok(exercises),
+            Ok(Some(exercises)) => result = Ok(exercises),
+            Ok(None) => {}
+            Err(e) if result.is_err() => result = Err(e.from_provider(provider.name())),
+            Err(_) => {}
+        }
+    }
+    result
+}
+
+pub(crate) fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
 }
@@ -0,0 +1,282 @@
+use super::NutritionInfo;
+use crate::crypto::Cipher;
+use crate::parser::{parse_portion, split_items};
+use crate::storage::{read_data, write_data};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    fetched: DateTime<Utc>,
+    provider: String,
+    items: Vec<NutritionInfo>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Entries {
+    /// Results of whole queries, by normalized query.
+    queries: HashMap<String, Entry>,
+    /// Single foods from those results, by normalized food name, with
+    /// nutrients for one unit so that later queries for other quantities of
+    /// them are answered too.
+    #[serde(default)]
+    foods: HashMap<String, Entry>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    entries: Entries,
+    hits: u64,
+    misses: u64,
+}
+
+/// What the cache holds, for display.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheStats {
+    pub queries: usize,
+    pub foods: usize,
+    pub expired: usize,
+    /// Lookups answered from the cache since it was opened.
+    pub hits: u64,
+    pub misses: u64,
+    /// Size of the cache file in bytes.
+    pub size: u64,
+}
+
+/// Provider responses kept on disk, so that repeated queries do not go to the
+/// network. Entries older than the expiry are fetched again, but are still
+/// served when every provider fails, such as when offline.
+pub struct NutritionCache {
+    path: Option<String>, // `None` keeps the cache in memory only
+    cipher: Option<Cipher>,
+    max_age: Duration,
+    state: Mutex<State>,
+}
+
+/// Lowercases `text` and collapses whitespace, so that trivially different
+/// spellings of a query share an entry.
+pub fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+impl NutritionCache {
+    /// A cache that is not saved anywhere.
+    pub fn in_memory(max_age: Duration) -> Self {
+        Self {
+            path: None,
+            cipher: None,
+            max_age,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Opens the cache file at `path`. A missing file gives an empty cache;
+    /// so does an unreadable or corrupt one, with a warning, and it is
+    /// replaced on the next insert.
+    pub fn load(path: &str, cipher: Option<&Cipher>, max_age: Duration) -> Self {
+        let entries = read_data(path, cipher).and_then(|contents| match contents {
+            Some(contents) => Ok(serde_json::from_slice(&contents)?),
+            None => Ok(Entries::default()),
+        });
+        let entries = entries.unwrap_or_else(|e| {
+            eprintln!(
+                "Warning: ignoring the nutrition cache {}, which will be replaced: {}",
+                path, e
+            );
+            Entries::default()
+        });
+        Self {
+            path: Some(path.to_string()),
+            cipher: cipher.cloned(),
+            max_age,
+            state: Mutex::new(State {
+                entries,
+                ..State::default()
+            }),
+        }
+    }
+
+    /// Writes the cache to `path` with `cipher`, leaving the file in use as
+    /// it is.
+    pub fn write_to(
+        &self,
+        path: &str,
+        cipher: Option<&Cipher>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let state = self.state.lock().map_err(|_| "Cache lock poisoned")?;
+        write_data(path, &serde_json::to_vec(&state.entries)?, cipher)
+    }
+
+    /// Keeps using the cache file at `path`, encrypted with `cipher`.
+    pub fn set_location(&mut self, path: &str, cipher: Option<&Cipher>) {
+        self.path = Some(path.to_string());
+        self.cipher = cipher.cloned();
+    }
+
+    pub fn set_max_age(&mut self, max_age: Duration) {
+        self.max_age = max_age;
+    }
+
+    fn write(&self, entries: &Entries) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match &self.path {
+            Some(path) => write_data(path, &serde_json::to_vec(entries)?, self.cipher.as_ref()),
+            None => Ok(()),
+        }
+    }
+
+    fn is_fresh(&self, entry: &Entry) -> bool {
+        Utc::now() - entry.fetched < self.max_age
+    }
+
+    fn find(&self, query: &str, fresh: bool) -> Option<(Vec<NutritionInfo>, String)> {
+        let mut state = self.state.lock().ok()?;
+        let found = state
+            .entries
+            .queries
+            .get(&normalize(query))
+            .filter(|entry| !fresh || self.is_fresh(entry))
+            .map(|entry| (entry.items.clone(), entry.provider.clone()))
+            .or_else(|| self.find_foods(&state.entries, query, fresh));
+        match &found {
+            Some(_) => state.hits += 1,
+            None if fresh => state.misses += 1,
+            None => {}
+        }
+        found
+    }
+
+    /// Answers `query` from the cached foods if it names only foods that are
+    /// cached in a matching unit, scaled to the quantities asked for.
+    fn find_foods(
+        &self,
+        entries: &Entries,
+        query: &str,
+        fresh: bool,
+    ) -> Option<(Vec<NutritionInfo>, String)> {
+        let mut items = Vec::new();
+        let mut provider = None;
+        for item in split_items(query) {
+            let portion = parse_portion(&item)?;
+            let entry = portion
+                .names()
+                .iter()
+                .find_map(|name| entries.foods.get(&normalize(name)))
+                .filter(|entry| !fresh || self.is_fresh(entry))?;
+            let per_unit = entry.items.first()?;
+            if !portion.is_in(&per_unit.unit) {
+                return None;
+            }
+            items.push(scale(per_unit, portion.quantity));
+            provider.get_or_insert_with(|| entry.provider.clone());
+        }
+        Some((items, provider?))
+    }
+
+    /// The cached results for `query` with the provider that found them,
+    /// unless they have expired.
+    pub fn get(&self, query: &str) -> Option<(Vec<NutritionInfo>, String)> {
+        self.find(query, true)
+    }
+
+    /// The cached results for `query`, however old.
+    pub fn get_stale(&self, query: &str) -> Option<(Vec<NutritionInfo>, String)> {
+        self.find(query, false)
+    }
+
+    /// Stores the results `provider` found for `query`, and each food of
+    /// them under its own name.
+    pub fn insert(
+        &self,
+        query: &str,
+        provider: &str,
+        items: &[NutritionInfo],
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut state = self.state.lock().map_err(|_| "Cache lock poisoned")?;
+        let entry = |items: Vec<NutritionInfo>| Entry {
+            fetched: Utc::now(),
+            provider: provider.to_string(),
+            items,
+        };
+        for item in items.iter().filter(|item| item.quantity > 0.0) {
+            let per_unit = NutritionInfo {
+                quantity: 1.0,
+                calories: item.calories / item.quantity,
+                protein: item.protein / item.quantity,
+                fat: item.fat / item.quantity,
+                carbs: item.carbs / item.quantity,
+                ..item.clone()
+            };
+            state
+                .entries
+                .foods
+                .insert(normalize(&item.name), entry(vec![per_unit]));
+        }
+        state
+            .entries
+            .queries
+            .insert(normalize(query), entry(items.to_vec()));
+        self.write(&state.entries)
+    }
+
+    /// Removes expired entries, or all entries when `all` is set. Returns the
+    /// number removed.
+    pub fn purge(&self, all: bool) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let mut state = self.state.lock().map_err(|_| "Cache lock poisoned")?;
+        let entries = &mut state.entries;
+        let before = entries.queries.len() + entries.foods.len();
+        entries
+            .queries
+            .retain(|_, entry| !all && self.is_fresh(entry));
+        entries
+            .foods
+            .retain(|_, entry| !all && self.is_fresh(entry));
+        let removed = before - entries.queries.len() - entries.foods.len();
+        self.write(&state.entries)?;
+        Ok(removed)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let state = match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let entries = &state.entries;
+        CacheStats {
+            queries: entries.queries.len(),
+            foods: entries.foods.len(),
+            expired: entries
+                .queries
+                .values()
+                .chain(entries.foods.values())
+                .filter(|entry| !self.is_fresh(entry))
+                .count(),
+            hits: state.hits,
+            misses: state.misses,
+            size: self
+                .path
+                .as_ref()
+                .and_then(|path| fs::metadata(path).ok())
+                .map_or(0, |metadata| metadata.len()),
+        }
+    }
+}
+
+/// `quantity` of a food cached per unit, rounded to hundredths.
+fn scale(per_unit: &NutritionInfo, quantity: f64) -> NutritionInfo {
+    let scaled = |value: f64| (value * quantity * 100.0).round() / 100.0;
+    NutritionInfo {
+        quantity,
+        calories: scaled(per_unit.calories),
+        protein: scaled(per_unit.protein),
+        fat: scaled(per_unit.fat),
+        carbs: scaled(per_unit.carbs),
+        ..per_unit.clone()
+    }
+}
@@ -0,0 +1,98 @@
+use reqwest::StatusCode;
+use std::error::Error;
+use std::fmt;
+
+/// Why a request to a nutrition provider failed. The message of each kind
+/// starts with the provider's name once it has passed through
+/// `search_and_get_nutrition` or `lookup_barcode`.
+#[derive(Debug)]
+pub enum ApiError {
+    /// No provider is configured, with the reason when one is known.
+    NotConfigured(Option<String>),
+    /// The provider could not be reached, did not answer in time or had a
+    /// server error.
+    Network(String),
+    /// The credentials were rejected.
+    Auth(String),
+    /// Too many requests, or the plan's quota is used up.
+    Quota(String),
+    /// The provider does not know the requested resource.
+    NotFound(String),
+    /// The response was not in the expected format.
+    Parse(String),
+    /// Any other unsuccessful HTTP status.
+    Status(u16, String),
+}
+
+impl ApiError {
+    /// The error for an unsuccessful HTTP status.
+    pub fn from_status(status: StatusCode) -> Self {
+        let message = status.to_string();
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ApiError::Auth(message),
+            StatusCode::PAYMENT_REQUIRED | StatusCode::TOO_MANY_REQUESTS => {
+                ApiError::Quota(message)
+            }
+            StatusCode::NOT_FOUND => ApiError::NotFound(message),
+            status if status.is_server_error() => ApiError::Network(message),
+            status => ApiError::Status(status.as_u16(), message),
+        }
+    }
+
+    /// Whether the request could not be answered without network access or
+    /// credentials, so a local lookup may stand in for it.
+    pub fn is_offline(&self) -> bool {
+        matches!(self, ApiError::NotConfigured(_) | ApiError::Network(_))
+    }
+
+    /// Prefixes the message with the name of the provider that failed.
+    pub fn from_provider(self, provider: &str) -> Self {
+        let prefix = |message: String| format!("{}: {}", provider, message);
+        match self {
+            ApiError::NotConfigured(reason) => ApiError::NotConfigured(reason),
+            ApiError::Network(message) => ApiError::Network(prefix(message)),
+            ApiError::Auth(message) => ApiError::Auth(prefix(message)),
+            ApiError::Quota(message) => ApiError::Quota(prefix(message)),
+            ApiError::NotFound(message) => ApiError::NotFound(prefix(message)),
+            ApiError::Parse(message) => ApiError::Parse(prefix(message)),
+            ApiError::Status(code, message) => ApiError::Status(code, prefix(message)),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::NotConfigured(Some(reason)) => write!(f, "API not configured: {}", reason),
+            ApiError::NotConfigured(None) => write!(
+                f,
+                "API not configured: set NUTRITIONIX_APP_ID and NUTRITIONIX_APP_KEY \
+                 or add a provider to the config file"
+            ),
+            ApiError::Network(message) => write!(f, "Network error: {}", message),
+            ApiError::Auth(message) => write!(f, "Invalid API credentials: {}", message),
+            ApiError::Quota(message) => write!(f, "API rate limit reached: {}", message),
+            ApiError::NotFound(message) => write!(f, "Not found: {}", message),
+            ApiError::Parse(message) => write!(f, "Unexpected API response: {}", message),
+            ApiError::Status(_, message) => write!(f, "API request failed: {}", message),
+        }
+    }
+}
+
+impl Error for ApiError {}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) => ApiError::from_status(status),
+            None if e.is_decode() => ApiError::Parse(e.to_string()),
+            None => ApiError::Network(e.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(e: serde_json::Error) -> Self {
+        ApiError::Parse(e.to_string())
+    }
+}
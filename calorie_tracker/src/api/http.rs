@@ -0,0 +1,96 @@
+use crate::config::HttpSettings;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, IntoUrl, RequestBuilder, Response, StatusCode};
+use std::error::Error;
+use std::time::Duration;
+
+/// HTTP client shared by all providers, so that connections are pooled.
+/// Requests that fail with a network error, a timeout, HTTP 429 or a 5xx
+/// status are retried with exponential backoff, waiting no longer in total
+/// than the request timeout.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: Client,
+    retries: u32,
+    backoff: Duration,
+    max_wait: Duration,
+}
+
+impl HttpClient {
+    pub fn new(settings: &HttpSettings) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let timeout = Duration::from_secs(settings.timeout_secs);
+        let client = Client::builder()
+            .timeout(timeout)
+            .connect_timeout(Duration::from_secs(settings.connect_timeout_secs))
+            .build()?;
+        Ok(Self {
+            client,
+            retries: settings.retries,
+            backoff: Duration::from_millis(settings.backoff_ms),
+            max_wait: timeout,
+        })
+    }
+
+    pub fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Sends `request`, retrying transient failures. After the last attempt,
+    /// or when the next wait would take the total past the request timeout,
+    /// the final response is returned whatever its status.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, reqwest::Error> {
+        let mut attempt = 0;
+        let mut waited = Duration::ZERO;
+        loop {
+            // Requests with a streaming body cannot be cloned and are sent once.
+            let retry = match request.try_clone() {
+                Some(retry) if attempt < self.retries => retry,
+                _ => return request.send().await,
+            };
+            let result = retry.send().await;
+            let delay = match &result {
+                Ok(response) if is_transient(response.status()) => {
+                    retry_after(response).unwrap_or_else(|| self.delay(attempt))
+                }
+                Err(e) if e.is_timeout() || e.is_connect() || e.is_request() => self.delay(attempt),
+                _ => return result,
+            };
+            waited += delay;
+            if waited > self.max_wait {
+                return result;
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Sends `request` without retrying, for requests that are only useful
+    /// if answered quickly.
+    pub async fn send_once(&self, request: RequestBuilder) -> Result<Response, reqwest::Error> {
+        request.send().await
+    }
+
+    fn delay(&self, attempt: u32) -> Duration {
+        self.backoff * 2u32.saturating_pow(attempt)
+    }
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// The delay a `Retry-After` header asks for, if it gives one in seconds.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds: u64 = response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds))
+}
@@ -0,0 +1,272 @@
+use super::{
+    local_timezone, round, ApiError, ExerciseInfo, HttpClient, NutritionInfo, NutritionProvider,
+    Person,
+};
+use crate::config::Credentials;
+use crate::products::Product;
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Suggestions arrive while the user types, so slow answers are dropped.
+const SUGGEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
+struct RequestBody<'a> {
+    query: &'a str,
+    timezone: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+struct ApiResponse {
+    foods: Vec<FoodItem>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FoodItem {
+    food_name: String,
+    serving_qty: f64,
+    serving_unit: String,
+    nf_calories: f64,
+    nf_total_fat: f64,
+    nf_protein: f64,
+    nf_total_carbohydrate: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct ExerciseRequest<'a> {
+    query: &'a str,
+    #[serde(flatten)]
+    person: &'a Person,
+}
+
+#[derive(Deserialize, Debug)]
+struct ExerciseResponse {
+    exercises: Vec<Exercise>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Exercise {
+    name: String,
+    duration_min: f64,
+    nf_calories: f64,
+}
+
+#[derive(Deserialize, Debug)]
+struct InstantResponse {
+    #[serde(default)]
+    common: Vec<InstantItem>,
+}
+
+#[derive(Deserialize, Debug)]
+struct InstantItem {
+    food_name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ItemResponse {
+    foods: Vec<Item>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Item {
+    food_name: String,
+    brand_name: Option<String>,
+    serving_qty: f64,
+    serving_unit: String,
+    serving_weight_grams: Option<f64>,
+    nf_calories: f64,
+    nf_total_fat: Option<f64>,
+    nf_protein: Option<f64>,
+    nf_total_carbohydrate: Option<f64>,
+}
+
+/// Nutritionix natural language API, which understands queries such as
+/// "2 apples, 200 grams of chicken".
+pub struct Nutritionix {
+    credentials: Credentials,
+    client: HttpClient,
+    base_url: String,
+}
+
+impl Nutritionix {
+    pub const BASE_URL: &'static str = "https://trackapi.nutritionix.com";
+
+    pub fn new(credentials: Credentials, client: HttpClient) -> Self {
+        Self {
+            credentials,
+            client,
+            base_url: Self::BASE_URL.to_string(),
+        }
+    }
+
+    /// Sends requests to `base_url` instead, such as a local test server.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+}
+
+#[async_trait]
+impl NutritionProvider for Nutritionix {
+    fn name(&self) -> &'static str {
+        "Nutritionix"
+    }
+
+    async fn search(&self, query: &str) -> Result<Vec<NutritionInfo>, ApiError> {
+        let url = format!("{}/v2/natural/nutrients", self.base_url);
+
+        let timezone = local_timezone();
+        let request_body = RequestBody {
+            query,
+            timezone: &timezone,
+        };
+
+        let request = self
+            .client
+            .post(url)
+            .header("x-app-id", &self.credentials.app_id)
+            .header("x-app-key", &self.credentials.app_key)
+            .json(&request_body);
+        let response = self.client.send(request).await?;
+
+        // Nutritionix answers 404 when it recognizes no food in the query.
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        if response.status().is_success() {
+            let response_text = response.text().await?;
+
+            let api_response: ApiResponse = serde_json::from_str(&response_text)?;
+
+            let nutrition_info = api_response
+                .foods
+                .into_iter()
+                .map(|food| {
+                    let carbs = food.nf_total_carbohydrate.unwrap_or_else(|| {
+                        (food.nf_calories - (food.nf_protein * 4.0) - (food.nf_total_fat * 9.0))
+                            / 4.0
+                    });
+
+                    NutritionInfo {
+                        name: food.food_name,
+                        quantity: food.serving_qty,
+                        unit: food.serving_unit,
+                        calories: food.nf_calories,
+                        protein: food.nf_protein,
+                        fat: food.nf_total_fat,
+                        carbs: round(carbs),
+                    }
+                })
+                .collect();
+
+            Ok(nutrition_info)
+        } else {
+            Err(ApiError::from_status(response.status()))
+        }
+    }
+
+    async fn lookup_barcode(&self, barcode: &str) -> Result<Option<Product>, ApiError> {
+        let url = format!("{}/v2/search/item", self.base_url);
+
+        let request = self
+            .client
+            .get(url)
+            .header("x-app-id", &self.credentials.app_id)
+            .header("x-app-key", &self.credentials.app_key)
+            .query(&[("upc", barcode)]);
+        let response = self.client.send(request).await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(ApiError::from_status(response.status()));
+        }
+        let found: ItemResponse = serde_json::from_str(&response.text().await?)?;
+
+        // Values are per serving; without the serving weight they cannot be
+        // converted to 100 g.
+        Ok(found.foods.into_iter().next().and_then(|item| {
+            let grams = item.serving_weight_grams.filter(|grams| *grams > 0.0)?;
+            let per_100g = |value: f64| round(value * 100.0 / grams);
+            Some(Product {
+                barcode: barcode.to_string(),
+                name: item.food_name,
+                brand: item.brand_name,
+                protein: per_100g(item.nf_protein.unwrap_or(0.0)),
+                fat: per_100g(item.nf_total_fat.unwrap_or(0.0)),
+                carbs: per_100g(item.nf_total_carbohydrate.unwrap_or(0.0)),
+                calories: per_100g(item.nf_calories),
+                serving_size: Some(format!("{} {}", item.serving_qty, item.serving_unit)),
+                serving_grams: Some(grams),
+            })
+        }))
+    }
+
+    /// Common foods from the instant search; branded items are left out as
+    /// the natural language endpoint cannot look them up by name.
+    async fn suggest(&self, query: &str) -> Result<Vec<String>, ApiError> {
+        let url = format!("{}/v2/search/instant", self.base_url);
+
+        let request = self
+            .client
+            .get(url)
+            .header("x-app-id", &self.credentials.app_id)
+            .header("x-app-key", &self.credentials.app_key)
+            .query(&[("query", query), ("branded", "false")])
+            .timeout(SUGGEST_TIMEOUT);
+        let response = self.client.send_once(request).await?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::from_status(response.status()));
+        }
+        let found: InstantResponse = serde_json::from_str(&response.text().await?)?;
+
+        let mut names: Vec<String> = Vec::new();
+        for item in found.common {
+            if !names.contains(&item.food_name) {
+                names.push(item.food_name);
+            }
+        }
+        Ok(names)
+    }
+
+    async fn search_exercise(
+        &self,
+        query: &str,
+        person: &Person,
+    ) -> Result<Option<Vec<ExerciseInfo>>, ApiError> {
+        let url = format!("{}/v2/natural/exercise", self.base_url);
+
+        let request = self
+            .client
+            .post(url)
+            .header("x-app-id", &self.credentials.app_id)
+            .header("x-app-key", &self.credentials.app_key)
+            .json(&ExerciseRequest { query, person });
+        let response = self.client.send(request).await?;
+
+        // As with foods, 404 means no exercise was recognized.
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(Some(Vec::new()));
+        }
+        if !response.status().is_success() {
+            return Err(ApiError::from_status(response.status()));
+        }
+        let found: ExerciseResponse = serde_json::from_str(&response.text().await?)?;
+
+        Ok(Some(
+            found
+                .exercises
+                .into_iter()
+                .map(|exercise| ExerciseInfo {
+                    name: exercise.name,
+                    duration_min: exercise.duration_min,
+                    calories: exercise.nf_calories,
+                })
+                .collect(),
+        ))
+    }
+}
@@ -0,0 +1,150 @@
+use super::{round, ApiError, HttpClient, NutritionInfo, NutritionProvider};
+use crate::import::open_food_facts::parse_product;
+use crate::products::Product;
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Open Food Facts asks API clients to identify themselves.
+pub const USER_AGENT: &str = concat!("calorie_tracker/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Deserialize, Debug)]
+struct SearchResponse {
+    #[serde(default)]
+    products: Vec<SearchProduct>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProductResponse {
+    #[serde(default)]
+    status: i64,
+    product: Option<Value>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SearchProduct {
+    #[serde(default)]
+    product_name: String,
+    #[serde(default)]
+    nutriments: Nutriments,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Nutriments {
+    #[serde(rename = "energy-kcal_100g")]
+    energy_kcal: Option<f64>,
+    #[serde(rename = "proteins_100g")]
+    proteins: Option<f64>,
+    #[serde(rename = "fat_100g")]
+    fat: Option<f64>,
+    #[serde(rename = "carbohydrates_100g")]
+    carbohydrates: Option<f64>,
+}
+
+/// Open Food Facts product search. It needs no credentials and is best for
+/// packaged products; results are per 100 g.
+pub struct OpenFoodFacts {
+    client: HttpClient,
+    base_url: String,
+}
+
+impl OpenFoodFacts {
+    pub const BASE_URL: &'static str = "https://world.openfoodfacts.org";
+
+    pub fn new(client: HttpClient) -> Self {
+        Self {
+            client,
+            base_url: Self::BASE_URL.to_string(),
+        }
+    }
+
+    /// Sends requests to `base_url` instead, such as a local test server.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+}
+
+#[async_trait]
+impl NutritionProvider for OpenFoodFacts {
+    fn name(&self) -> &'static str {
+        "Open Food Facts"
+    }
+
+    async fn search(&self, query: &str) -> Result<Vec<NutritionInfo>, ApiError> {
+        let url = format!("{}/cgi/search.pl", self.base_url);
+
+        let request = self
+            .client
+            .get(url)
+            .header("User-Agent", USER_AGENT)
+            .query(&[
+                ("search_terms", query),
+                ("search_simple", "1"),
+                ("json", "1"),
+                ("page_size", "5"),
+                ("fields", "product_name,nutriments"),
+            ]);
+        let response = self.client.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::from_status(response.status()));
+        }
+        let search: SearchResponse = serde_json::from_str(&response.text().await?)?;
+
+        // Many products lack nutrition facts; use the first one that has them.
+        Ok(search
+            .products
+            .into_iter()
+            .filter(|p| !p.product_name.is_empty() && p.nutriments.energy_kcal.is_some())
+            .take(1)
+            .map(|product| {
+                let n = product.nutriments;
+                NutritionInfo {
+                    name: product.product_name,
+                    quantity: 100.0,
+                    unit: "g".to_string(),
+                    calories: n.energy_kcal.unwrap_or(0.0),
+                    protein: n.proteins.unwrap_or(0.0),
+                    fat: n.fat.unwrap_or(0.0),
+                    carbs: round(n.carbohydrates.unwrap_or(0.0)),
+                }
+            })
+            .collect())
+    }
+
+    async fn lookup_barcode(&self, barcode: &str) -> Result<Option<Product>, ApiError> {
+        let url = format!("{}/api/v2/product/{}.json", self.base_url, barcode);
+
+        let request = self
+            .client
+            .get(url)
+            .header("User-Agent", USER_AGENT)
+            .query(&[(
+                "fields",
+                "code,product_name,brands,serving_size,serving_quantity,nutriments",
+            )]);
+        let response = self.client.send(request).await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(ApiError::from_status(response.status()));
+        }
+        let found: ProductResponse = serde_json::from_str(&response.text().await?)?;
+        match found.product {
+            Some(mut product) if found.status == 1 => {
+                // The code is echoed at the top level, not inside the product.
+                if let Some(fields) = product.as_object_mut() {
+                    fields
+                        .entry("code")
+                        .or_insert_with(|| Value::String(barcode.to_string()));
+                }
+                Ok(parse_product(product)?)
+            }
+            _ => Ok(None),
+        }
+    }
+}
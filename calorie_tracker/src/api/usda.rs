@@ -0,0 +1,150 @@
+use super::{round, ApiError, HttpClient, NutritionInfo, NutritionProvider};
+use crate::products::{barcode_key, Product};
+use async_trait::async_trait;
+use serde::Deserialize;
+
+// FoodData Central nutrient IDs.
+const ENERGY_KCAL: u32 = 1008;
+const PROTEIN: u32 = 1003;
+const FAT: u32 = 1004;
+const CARBS: u32 = 1005;
+
+#[derive(Deserialize, Debug)]
+struct SearchResponse {
+    foods: Vec<FoodItem>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct FoodItem {
+    description: String,
+    #[serde(default)]
+    food_nutrients: Vec<FoodNutrient>,
+    gtin_upc: Option<String>,
+    brand_owner: Option<String>,
+    serving_size: Option<f64>,
+    serving_size_unit: Option<String>,
+    household_serving_full_text: Option<String>,
+}
+
+impl FoodItem {
+    fn nutrient(&self, id: u32) -> f64 {
+        self.food_nutrients
+            .iter()
+            .find(|n| n.nutrient_id == id)
+            .map(|n| n.value)
+            .unwrap_or(0.0)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct FoodNutrient {
+    nutrient_id: u32,
+    #[serde(default)]
+    value: f64,
+}
+
+/// USDA FoodData Central search. It matches a single food by name rather
+/// than parsing quantities, so results are per 100 g.
+pub struct Usda {
+    api_key: String,
+    client: HttpClient,
+    base_url: String,
+}
+
+impl Usda {
+    pub const BASE_URL: &'static str = "https://api.nal.usda.gov/fdc";
+
+    pub fn new(api_key: String, client: HttpClient) -> Self {
+        Self {
+            api_key,
+            client,
+            base_url: Self::BASE_URL.to_string(),
+        }
+    }
+
+    /// Sends requests to `base_url` instead, such as a local test server.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+}
+
+#[async_trait]
+impl NutritionProvider for Usda {
+    fn name(&self) -> &'static str {
+        "USDA FoodData Central"
+    }
+
+    async fn search(&self, query: &str) -> Result<Vec<NutritionInfo>, ApiError> {
+        let url = format!("{}/v1/foods/search", self.base_url);
+
+        let request = self.client.get(url).query(&[
+            ("query", query),
+            ("pageSize", "1"),
+            ("api_key", &self.api_key),
+        ]);
+        let response = self.client.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::from_status(response.status()));
+        }
+        let search: SearchResponse = serde_json::from_str(&response.text().await?)?;
+
+        Ok(search
+            .foods
+            .into_iter()
+            .map(|food| NutritionInfo {
+                name: food.description.to_lowercase(),
+                quantity: 100.0,
+                unit: "g".to_string(),
+                calories: food.nutrient(ENERGY_KCAL),
+                protein: food.nutrient(PROTEIN),
+                fat: food.nutrient(FAT),
+                carbs: round(food.nutrient(CARBS)),
+            })
+            .collect())
+    }
+
+    /// Branded foods are searchable by their UPC.
+    async fn lookup_barcode(&self, barcode: &str) -> Result<Option<Product>, ApiError> {
+        let url = format!("{}/v1/foods/search", self.base_url);
+
+        let request = self.client.get(url).query(&[
+            ("query", barcode),
+            ("dataType", "Branded"),
+            ("pageSize", "5"),
+            ("api_key", &self.api_key),
+        ]);
+        let response = self.client.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::from_status(response.status()));
+        }
+        let search: SearchResponse = serde_json::from_str(&response.text().await?)?;
+
+        let key = barcode_key(barcode);
+        Ok(search
+            .foods
+            .into_iter()
+            .find(|food| key.is_some() && food.gtin_upc.as_deref().and_then(barcode_key) == key)
+            .map(|food| {
+                let in_grams = food
+                    .serving_size_unit
+                    .as_deref()
+                    .is_some_and(|unit| unit.eq_ignore_ascii_case("g") || unit == "GRM");
+                Product {
+                    barcode: barcode.to_string(),
+                    name: food.description.clone(),
+                    brand: food.brand_owner.clone(),
+                    protein: food.nutrient(PROTEIN),
+                    fat: food.nutrient(FAT),
+                    carbs: round(food.nutrient(CARBS)),
+                    calories: food.nutrient(ENERGY_KCAL),
+                    serving_size: food.household_serving_full_text.clone(),
+                    serving_grams: food.serving_size.filter(|_| in_grams),
+                }
+            }))
+    }
+}
@@ -1,41 +1,100 @@
-use crate::api;
-use crate::day::Day;
-use crate::food::Food;
-use crate::storage::{load_days, save_days};
-use chrono::{Local, NaiveDate};
+use crate::api::cache::CacheStats;
+use crate::api::{self, ApiError, HttpClient, NutritionCache, NutritionInfo, NutritionProvider};
+use crate::config::Config;
+use crate::crypto::Cipher;
+use crate::day::{index_days, Day, Days};
+use crate::food::{Food, Meal};
+use crate::history::{load_history, save_history, Action, History};
+use crate::import;
+use crate::import::open_food_facts::Filter;
+use crate::journal::{self, Change, Event, Journal};
+use crate::library::{Library, LibraryFood};
+use crate::parser::{self, ParsedFoods};
+use crate::products::{Product, ProductStore};
+use crate::storage::{read_data, write_data, Layout, LockMode, Store};
+use crate::validate::{repair, validate, validate_day, Problem, Report};
+use chrono::{DateTime, Datelike, Local, NaiveDate};
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
 use serde::{Deserialize, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeSet;
 use std::error::Error;
+use std::fs;
 use tokio::runtime::Runtime;
 
 pub type AppResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
 
 pub struct App {
-    days: Vec<Day>,
-    file_path: String,
-    current_day_index: usize,
+    days: Days,
+    store: Store,
+    cipher: Option<Cipher>,
+    journal: Journal,
+    events_since_snapshot: u64,
+    unsaved_dates: BTreeSet<NaiveDate>, // days changed since the last snapshot
+    archived: BTreeSet<NaiveDate>,      // loaded days that are kept in an archive
+    archive_years: BTreeSet<i32>,       // years with an archive file
+    loaded_years: BTreeSet<i32>,        // archived years read into `days`
+    current_date: NaiveDate,
+    load_report: Report, // problems in the data as stored on disk
+    history: History,
     matcher: SkimMatcherV2,
-    user_height: f32, // in centimeters
-    user_weight: f32, // in kilograms
-    user_age: u32,
-    user_gender: Gender,
+    profile: Profile,
+    config: Config,
+    providers: Vec<Box<dyn NutritionProvider>>,
+    runtime: Runtime, // runs provider requests
+    http: HttpClient,
+    library: Option<Library>, // loaded on first use
+    api_cache: NutritionCache,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+/// A food or workout entry, or a whole day, as it was when a change to it was
+/// made, so the change can be checked against what other instances saved in
+/// the meantime.
+enum Seen {
+    Food(Food),
+    Workout(Workout),
+    /// The day an undo or redo expects to replace; `None` if it should not
+    /// exist.
+    Day(Option<Day>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Gender {
     Male,
     Female,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub height: f32, // in centimeters
+    pub weight: f32, // in kilograms
+    pub age: u32,
+    pub gender: Gender,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            height: 180.0,
+            weight: 79.0,
+            age: 22,
+            gender: Gender::Male,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum WorkoutType {
     WeightLifting,
     Cardio,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Workout {
+    /// What was done, such as "running", for workouts described in words.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub workout_type: WorkoutType,
     pub duration: u32, // in minutes
     pub calories_burnt: u32,
@@ -43,35 +102,153 @@ pub struct Workout {
 
 impl App {
     pub fn new(file_path: &str) -> AppResult<Self> {
-        let mut days = load_days(file_path)?;
-        if days.is_empty() {
-            days.push(Day::new(Local::now().date_naive()));
-        }
-        Ok(Self {
-            days,
-            file_path: file_path.to_string(),
-            current_day_index: 0,
+        Self::open(file_path, None)
+    }
+
+    /// Opens the data file, using `passphrase` to decrypt it. A passphrase for
+    /// a file that does not exist yet creates an encrypted data file. If
+    /// `file_path` is a directory, it is read with one file per day.
+    pub fn open(file_path: &str, passphrase: Option<&str>) -> AppResult<Self> {
+        Self::open_store(Store::new(file_path), passphrase)
+    }
+
+    pub fn open_store(store: Store, passphrase: Option<&str>) -> AppResult<Self> {
+        store.create()?;
+        let _lock = store.lock(LockMode::Exclusive)?;
+        let cipher = match passphrase {
+            None => None,
+            Some(passphrase) if store.is_encrypted()? => Some(Cipher::for_blob(
+                passphrase,
+                &fs::read(store.snapshot_path())?,
+            )?),
+            Some(_) if store.exists() => {
+                return Err(format!("{} is not encrypted", store.path()).into())
+            }
+            Some(passphrase) => Some(Cipher::new(passphrase)?),
+        };
+        let snapshot = store.load_snapshot(cipher.as_ref())?;
+        let load_report = validate(&snapshot.days);
+        let journal = Journal::open(
+            &store.journal_path(),
+            snapshot.offset,
+            snapshot.seq,
+            cipher.clone(),
+        );
+        let history = load_history(&store.history_path(), cipher.as_ref())?;
+        let profile = match read_data(&store.profile_path(), cipher.as_ref())? {
+            Some(contents) => serde_json::from_slice(&contents)?,
+            None => Profile::default(),
+        };
+        let config = Config::from_env();
+        let api_cache = NutritionCache::load(
+            &store.api_cache_path(),
+            cipher.as_ref(),
+            config.cache_max_age(),
+        );
+        let today = Local::now().date_naive();
+        let mut app = Self {
+            days: index_days(snapshot.days),
+            archive_years: store.archived_years()?,
+            store,
+            cipher,
+            journal,
+            events_since_snapshot: 0,
+            unsaved_dates: BTreeSet::new(),
+            archived: BTreeSet::new(),
+            loaded_years: BTreeSet::new(),
+            current_date: today,
+            load_report,
+            history,
             matcher: SkimMatcherV2::default(),
-            user_height: 180.0, // Default values
-            user_weight: 79.0,
-            user_age: 22,
-            user_gender: Gender::Male,
-        })
+            profile,
+            http: HttpClient::new(&config.http)?,
+            config,
+            runtime: Runtime::new()?,
+            providers: Vec::new(),
+            library: None,
+            api_cache,
+        };
+        app.providers = api::providers(&app.config, &app.http);
+
+        let events = app.journal.read_new()?;
+        for event in &events {
+            app.apply(&event.change)?;
+        }
+        app.events_since_snapshot = events.len() as u64;
+        // Data written before the journal existed becomes its first event, so
+        // the whole history can be replayed from the journal alone.
+        let imported = app.journal.offset() == 0 && !app.days.is_empty();
+        if imported {
+            app.journal.append(Change::Imported {
+                days: app.days.values().cloned().collect(),
+            })?;
+        }
+        // Open on today. The day is only saved once something is logged.
+        app.load_range(today - chrono::Duration::days(6), today)?;
+        app.days.entry(today).or_insert_with(|| Day::new(today));
+        app.current_date = today;
+
+        let due = app.events_since_snapshot >= app.store.snapshot_interval();
+        if (imported || due) && app.check().is_ok() {
+            app.save_snapshot(true)?;
+        }
+        Ok(app)
     }
 
     pub fn add_food(&mut self, food: Food, quantity: f64) -> AppResult<()> {
-        self.get_current_day_mut()?.add_food(food, quantity);
-        self.save()
+        let description = format!("Add {}", food.name);
+        let day = self.get_current_day()?;
+        let date = day.date;
+        match day.foods.iter().position(|f| f.combines_with(&food)) {
+            Some(index) => {
+                let mut combined = day.foods[index].clone();
+                combined.combine(&food, quantity);
+                let change = Change::FoodEdited {
+                    date,
+                    index,
+                    food: combined,
+                };
+                let seen = Seen::Food(day.foods[index].clone());
+                self.commit_entry(change, seen, &description)
+            }
+            None => {
+                let change = Change::FoodAdded {
+                    date,
+                    food,
+                    quantity,
+                };
+                self.commit(change, Some(&description))
+            }
+        }
     }
 
     pub fn remove_food(&mut self, index: usize) -> AppResult<()> {
-        self.get_current_day_mut()?.remove_food(index);
-        self.save()
+        let day = self.get_current_day()?;
+        let date = day.date;
+        let Some(food) = day.foods.get(index) else {
+            return Ok(());
+        };
+        let description = format!("Remove {}", food.name);
+        let seen = Seen::Food(food.clone());
+        self.commit_entry(Change::FoodRemoved { date, index }, seen, &description)
+    }
+
+    /// Replaces the food at `index` on `date`.
+    pub fn edit_food(&mut self, date: NaiveDate, index: usize, food: Food) -> AppResult<()> {
+        self.load_range(date, date)?;
+        let Some(old) = self.days.get(&date).and_then(|day| day.foods.get(index)) else {
+            return Err(format!("No food #{} on {}", index + 1, date).into());
+        };
+        let description = format!("Edit {}", old.name);
+        let seen = Seen::Food(old.clone());
+        self.commit_entry(Change::FoodEdited { date, index, food }, seen, &description)
     }
 
+    /// Searches the foods of all loaded days; archived years that have not
+    /// been loaded are left out.
     pub fn search_food(&self, query: &str) -> Vec<(Food, i64)> {
         self.days
-            .iter()
+            .values()
             .flat_map(|day| day.foods.iter())
             .filter_map(|food| {
                 self.matcher
@@ -82,68 +259,273 @@ impl App {
     }
 
     pub fn add_food_manually(&mut self, food: Food) -> AppResult<()> {
-        self.get_current_day_mut()?.add_food(food, 1.0);
-        self.save()
-    }
-
-    pub fn search_and_add_food(&mut self, query: &str) -> AppResult<Vec<Food>> {
-        let rt = Runtime::new()?;
-        let nutrition_info = rt.block_on(api::search_and_get_nutrition(query));
-
-        match nutrition_info {
-            Ok(info) => {
-                let mut added_foods = Vec::new();
-
-                for info in info {
-                    let food = Food::new(
-                        &info.name,
-                        info.quantity,
-                        &info.unit,
-                        info.protein,
-                        info.fat,
-                        info.carbs,
-                        info.calories,
-                    );
-                    self.add_food(food.clone(), 1.0)?;
-                    added_foods.push(food);
-                }
+        self.add_food(food, 1.0)
+    }
+
+    /// Looks up `query` with the configured nutrition providers without
+    /// logging anything, so the results can be reviewed with the user first.
+    /// Fails with an `ApiError`, `ApiError::NotConfigured` when no provider
+    /// is set up.
+    pub fn search_nutrition(&self, query: &str) -> AppResult<Vec<NutritionInfo>> {
+        self.runtime
+            .block_on(api::search_and_get_nutrition(
+                query,
+                &self.providers,
+                Some(&self.api_cache),
+            ))
+            .map_err(|e| self.explain(e).into())
+    }
+
+    /// Food names the providers suggest for a partly typed `query`.
+    pub fn suggest_foods(&self, query: &str) -> AppResult<Vec<String>> {
+        self.runtime
+            .block_on(api::suggest(query, &self.providers))
+            .map_err(|e| self.explain(e).into())
+    }
 
-                Ok(added_foods)
+    /// `error` with the reason no provider is configured, when the config
+    /// knows one.
+    fn explain(&self, error: ApiError) -> ApiError {
+        match error {
+            ApiError::NotConfigured(None) => {
+                ApiError::NotConfigured(self.config.env_problem.clone())
             }
-            Err(e) => {
-                println!("API Error: {}. Falling back to manual entry.", e);
-                Ok(Vec::new())
+            error => error,
+        }
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.api_cache.stats()
+    }
+
+    /// Removes expired provider results from the cache, or all of them when
+    /// `all` is set. Returns the number of entries removed.
+    pub fn purge_cache(&self, all: bool) -> AppResult<usize> {
+        self.api_cache.purge(all)
+    }
+
+    /// Logs reviewed nutrition results on the current day, at `meal` if given.
+    pub fn add_nutrition(
+        &mut self,
+        items: &[NutritionInfo],
+        meal: Option<Meal>,
+    ) -> AppResult<Vec<Food>> {
+        let mut added_foods = Vec::new();
+        for info in items {
+            let food = info.to_food().with_meal(meal);
+            self.add_food(food.clone(), food.quantity)?;
+            added_foods.push(food);
+        }
+        Ok(added_foods)
+    }
+
+    /// Looks up `query` without logging anything. Without a provider or
+    /// network access, the foods are looked up in the library.
+    pub fn find_foods(&mut self, query: &str) -> AppResult<Vec<NutritionInfo>> {
+        match self.search_nutrition(query) {
+            Ok(items) => Ok(items),
+            Err(e)
+                if e.downcast_ref::<ApiError>()
+                    .is_some_and(ApiError::is_offline) =>
+            {
+                let parsed = self.parse_foods(query)?;
+                if parsed.foods.is_empty() {
+                    return Err(e);
+                }
+                Ok(parsed.foods)
             }
+            Err(e) => Err(e),
         }
     }
 
+    /// Looks up `query` and logs everything found without review.
+    pub fn search_and_add_food(&mut self, query: &str, meal: Option<Meal>) -> AppResult<Vec<Food>> {
+        let nutrition_info = self.find_foods(query)?;
+        self.add_nutrition(&nutrition_info, meal)
+    }
+
+    /// Understands a description such as "2 eggs and 150g chicken breast"
+    /// offline, by matching each food against the library.
+    pub fn parse_foods(&mut self, query: &str) -> AppResult<ParsedFoods> {
+        self.library()?;
+        let library = self.library.as_ref().ok_or("Library not loaded")?;
+        Ok(parser::parse_foods(query, library, &self.matcher))
+    }
+
+    /// The library of imported foods, loaded on first use.
+    pub fn library(&mut self) -> AppResult<&Library> {
+        let library = match self.library.take() {
+            Some(library) => library,
+            None => Library::load(&self.store.library_path(), self.cipher.as_ref())?,
+        };
+        Ok(self.library.insert(library))
+    }
+
+    /// Searches the food library by name, best match first.
+    pub fn search_library(
+        &mut self,
+        query: &str,
+        limit: usize,
+    ) -> AppResult<Vec<(LibraryFood, i64)>> {
+        self.library()?;
+        let library = self.library.as_ref().ok_or("Library not loaded")?;
+        Ok(library
+            .search(&self.matcher, query)
+            .into_iter()
+            .take(limit)
+            .map(|(food, score)| (food.clone(), score))
+            .collect())
+    }
+
+    /// Imports a USDA FoodData Central download into the food library. Returns
+    /// the number of foods added or updated.
+    pub fn import_usda(&mut self, path: &str) -> AppResult<usize> {
+        let foods = import::usda::import(path)?;
+        self.import_foods(foods)
+    }
+
+    fn import_foods(&mut self, foods: Vec<LibraryFood>) -> AppResult<usize> {
+        let _lock = self.store.lock(LockMode::Exclusive)?;
+        // Reload first so foods another instance imported are kept.
+        let mut library = Library::load(&self.store.library_path(), self.cipher.as_ref())?;
+        let count = library.extend(foods);
+        library.save(&self.store.library_path(), self.cipher.as_ref())?;
+        self.library = Some(library);
+        Ok(count)
+    }
+
+    /// The packaged product database.
+    pub fn products(&self) -> ProductStore {
+        ProductStore::new(&self.store.products_dir())
+    }
+
+    /// Imports an Open Food Facts dump into the product database. Returns the
+    /// number of products added or updated.
+    pub fn import_open_food_facts(&mut self, path: &str, filter: &Filter) -> AppResult<usize> {
+        let _lock = self.store.lock(LockMode::Exclusive)?;
+        let products = self.products();
+        let mut writer = products.writer()?;
+        import::open_food_facts::import(path, filter, |product| writer.add(&product))?;
+        writer.finish()
+    }
+
+    /// Products fetched from providers by barcode, kept apart from the
+    /// imported database so adding one does not rewrite a large import.
+    pub fn cached_products(&self) -> ProductStore {
+        ProductStore::new(&self.store.products_cache_dir())
+    }
+
+    /// Looks up a packaged product by barcode in the product database and
+    /// the cached products.
+    pub fn find_product(&self, barcode: &str) -> AppResult<Option<Product>> {
+        let _lock = self.store.lock(LockMode::Shared)?;
+        match self.products().find_barcode(barcode)? {
+            Some(product) => Ok(Some(product)),
+            None => self.cached_products().find_barcode(barcode),
+        }
+    }
+
+    /// Searches the product database and cached products by name and brand,
+    /// best match first.
+    pub fn search_products(&self, query: &str, limit: usize) -> AppResult<Vec<(Product, i64)>> {
+        let _lock = self.store.lock(LockMode::Shared)?;
+        let mut results = self.products().search(&self.matcher, query, limit)?;
+        results.extend(self.cached_products().search(&self.matcher, query, limit)?);
+        results.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+        results.truncate(limit);
+        Ok(results)
+    }
+
+    /// Looks up a packaged product by barcode: first in the food library and
+    /// the local products, then with the configured providers. Products found
+    /// online are cached. Returns the product and where it was found.
+    pub fn lookup_barcode(&mut self, barcode: &str) -> AppResult<Option<(Product, &'static str)>> {
+        if let Some(food) = self.library()?.find_barcode(barcode) {
+            return Ok(Some((food.to_product(barcode), "food library")));
+        }
+        if let Some(product) = self.find_product(barcode)? {
+            return Ok(Some((product, "local products")));
+        }
+
+        let found = self
+            .runtime
+            .block_on(api::lookup_barcode(barcode, &self.providers))
+            .map_err(|e| self.explain(e))?;
+        if let Some((product, _)) = &found {
+            let _lock = self.store.lock(LockMode::Exclusive)?;
+            self.cached_products().insert(product.clone())?;
+        }
+        Ok(found)
+    }
+
     pub fn get_all_foods(&self) -> Vec<&Food> {
-        self.days.iter().flat_map(|day| &day.foods).collect()
+        self.days.values().flat_map(|day| &day.foods).collect()
     }
 
+    /// Switches to `date`. A day with nothing recorded is only kept in
+    /// memory until something is logged on it, and forgotten again when left
+    /// empty. The archived week leading up to it is loaded so the weekly
+    /// reports are complete.
     pub fn change_day(&mut self, date: NaiveDate) -> AppResult<()> {
-        if let Some(index) = self.days.iter().position(|day| day.date == date) {
-            self.current_day_index = index;
-            Ok(())
-        } else {
-            Err("Date not found".into())
+        self.load_range(date - chrono::Duration::days(6), date)?;
+        let left = self.current_date;
+        let blank = self
+            .days
+            .get(&left)
+            .is_some_and(|day| day.foods.is_empty() && day.workouts.is_empty())
+            && !self.unsaved_dates.contains(&left)
+            && !self.archived.contains(&left);
+        if left != date && blank {
+            self.days.remove(&left);
         }
+        self.days.entry(date).or_insert_with(|| Day::new(date));
+        self.current_date = date;
+        Ok(())
+    }
+
+    pub fn previous_day(&mut self) -> AppResult<()> {
+        let date = self.current_date.pred_opt().ok_or("No earlier date")?;
+        self.change_day(date)
     }
 
+    pub fn next_day(&mut self) -> AppResult<()> {
+        let date = self.current_date.succ_opt().ok_or("No later date")?;
+        self.change_day(date)
+    }
+
+    pub fn go_to_today(&mut self) -> AppResult<()> {
+        self.change_day(Local::now().date_naive())
+    }
+
+    /// Dates from `start` to `end` with foods or workouts logged.
+    pub fn dates_with_data(
+        &mut self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> AppResult<BTreeSet<NaiveDate>> {
+        self.load_range(start, end)?;
+        Ok(self
+            .days_in_range(start, end)
+            .filter(|day| !day.foods.is_empty() || !day.workouts.is_empty())
+            .map(|day| day.date)
+            .collect())
+    }
+
+    /// Position of the current day among the recorded days, starting at 1.
     pub fn current_day(&self) -> usize {
-        self.current_day_index + 1
+        self.days.range(..=self.current_date).count()
     }
 
     pub fn get_current_day(&self) -> AppResult<&Day> {
         self.days
-            .get(self.current_day_index)
+            .get(&self.current_date)
             .ok_or_else(|| "No days recorded".into())
     }
 
-    pub fn get_current_day_mut(&mut self) -> AppResult<&mut Day> {
-        self.days
-            .get_mut(self.current_day_index)
-            .ok_or_else(|| "No days recorded".into())
+    /// Recorded days from `start` to `end` inclusive, in date order. Archived
+    /// days are only included once `load_range` has loaded them.
+    pub fn days_in_range(&self, start: NaiveDate, end: NaiveDate) -> impl Iterator<Item = &Day> {
+        self.days.range(start..=end).map(|(_, day)| day)
     }
 
     pub fn calculate_recommended_protein(&self, workouts_per_week: u32) -> f32 {
@@ -154,87 +536,655 @@ impl App {
             _ => 1.4,
         };
 
-        self.user_weight * activity_factor
+        self.profile.weight * activity_factor
     }
 
-    pub fn get_week_protein_and_calories(&self) -> Vec<(NaiveDate, u32, f32, Option<&Workout>)> {
-        let current_date = self.get_current_day().unwrap().date;
-        let week_start = current_date - chrono::Duration::days(6);
+    pub fn get_week_protein_and_calories(&self) -> Vec<(NaiveDate, u32, f32, &[Workout])> {
+        self.week_ending(self.get_current_day().unwrap().date)
+    }
+
+    /// Totals of the seven days up to `date`, including days with nothing
+    /// recorded. Archived days are only included once `load_range` has
+    /// loaded them.
+    pub fn week_ending(&self, date: NaiveDate) -> Vec<(NaiveDate, u32, f32, &[Workout])> {
+        let week_start = date - chrono::Duration::days(6);
 
         (0..7)
             .map(|i| {
                 let date = week_start + chrono::Duration::days(i);
-                let day = self.days.iter().find(|day| day.date == date);
+                let day = self.days.get(&date);
                 let calories = day.map(|d| d.total_calories()).unwrap_or(0.0);
                 let protein = day.map(|d| d.total_protein()).unwrap_or(0.0);
-                let workout = day.and_then(|d| d.workout.as_ref());
-                (date, calories as u32, protein as f32, workout)
+                let workouts = day.map_or(&[][..], |d| &d.workouts);
+                (date, calories as u32, protein as f32, workouts)
             })
             .collect()
     }
 
-    fn save(&self) -> AppResult<()> {
-        save_days(&self.file_path, &self.days)
+    /// Records `change` in the journal and applies it to the days, first
+    /// picking up any events other processes appended since our last sync.
+    /// With a `description`, the change is also recorded for undo.
+    fn commit(&mut self, change: Change, description: Option<&str>) -> AppResult<()> {
+        self.commit_and(change, None, |history, date, before, after| {
+            if let Some(description) = description {
+                history.record(Action {
+                    description: description.to_string(),
+                    date,
+                    before,
+                    after,
+                });
+            }
+        })
+    }
+
+    /// Commits a change to the entry at the change's index, which the caller
+    /// saw as `seen`. If other instances moved the entry in the meantime, the
+    /// change follows it; if they changed or removed it, the change is
+    /// refused.
+    fn commit_entry(&mut self, change: Change, seen: Seen, description: &str) -> AppResult<()> {
+        self.commit_and(change, Some(seen), |history, date, before, after| {
+            history.record(Action {
+                description: description.to_string(),
+                date,
+                before,
+                after,
+            });
+        })
     }
-    pub fn reset_day(&mut self) -> AppResult<()> {
-        self.get_current_day_mut()?.reset();
-        self.save()
+
+    /// Like `commit`, with `update` given the undo history and the day before
+    /// and after the change once it is in the journal, before the history is
+    /// saved.
+    fn commit_and(
+        &mut self,
+        change: Change,
+        seen: Option<Seen>,
+        update: impl FnOnce(&mut History, NaiveDate, Option<Day>, Option<Day>),
+    ) -> AppResult<()> {
+        let _lock = self.store.lock(LockMode::Exclusive)?;
+        self.sync()?;
+        self.commit_synced(change, seen, update)
     }
-    pub fn register_day(&mut self) -> AppResult<()> {
+
+    /// `commit_and` for callers that already hold the lock and have synced.
+    fn commit_synced(
+        &mut self,
+        mut change: Change,
+        seen: Option<Seen>,
+        update: impl FnOnce(&mut History, NaiveDate, Option<Day>, Option<Day>),
+    ) -> AppResult<()> {
+        let date = change.date().ok_or("Change must target a single day")?;
+        self.load_range(date, date)?;
+        let day = self.days.get(&date);
+        let moved = "The entry was changed by another instance; check the day and try again";
+        match (seen, change.index_mut()) {
+            (Some(Seen::Day(expected)), _) if !same_day(day, expected.as_ref()) => {
+                return Err(
+                    "The day was changed by another instance; check it and try again".into(),
+                );
+            }
+            (Some(Seen::Food(food)), Some(index)) => {
+                *index = day
+                    .and_then(|day| find_entry(&day.foods, *index, &food))
+                    .ok_or(moved)?;
+            }
+            (Some(Seen::Workout(workout)), Some(index)) => {
+                *index = day
+                    .and_then(|day| find_entry(&day.workouts, *index, &workout))
+                    .ok_or(moved)?;
+            }
+            _ => {}
+        }
+        let before = self.days.get(&date).cloned();
+        let mut changed: Days = before.iter().map(|day| (date, day.clone())).collect();
+        change.apply(&mut changed);
+        if let Some(problem) = changed.values().flat_map(validate_day).next() {
+            return Err(format!("Refusing to save invalid data: {}", problem).into());
+        }
+
+        change.apply(&mut self.days);
+        let after = self.days.get(&date).cloned();
+        self.journal.append(change)?;
+        self.events_since_snapshot += 1;
+        self.unsaved_dates.insert(date);
+        self.select_day(date);
+
+        update(&mut self.history, date, before, after);
+        save_history(
+            &self.store.history_path(),
+            &self.history,
+            self.cipher.as_ref(),
+        )?;
+
+        if self.events_since_snapshot >= self.store.snapshot_interval() && self.check().is_ok() {
+            self.save_snapshot(false)?;
+        }
+        Ok(())
+    }
+
+    /// Applies events other processes appended since our last sync, and
+    /// reloads the undo history they share. The caller must hold the file
+    /// lock.
+    fn sync(&mut self) -> AppResult<()> {
         let current_date = self.get_current_day()?.date;
-        let new_date = current_date.succ_opt().ok_or("Failed to get next date")?;
-        if !self.days.iter().any(|day| day.date == new_date) {
-            self.days.push(Day::new(new_date));
-            self.current_day_index = self.days.len() - 1;
-            self.save()?;
+        for event in self.journal.read_new()? {
+            self.apply(&event.change)?;
+            self.events_since_snapshot += 1;
+            self.unsaved_dates.extend(event.change.date());
+        }
+        self.history = load_history(&self.store.history_path(), self.cipher.as_ref())?;
+        self.select_day(current_date);
+        Ok(())
+    }
+
+    /// Applies a journaled change, loading any archived days it touches first.
+    fn apply(&mut self, change: &Change) -> AppResult<()> {
+        match change.date() {
+            Some(date) => self.load_range(date, date)?,
+            None => self.load_range(NaiveDate::MIN, NaiveDate::MAX)?,
+        }
+        change.apply(&mut self.days);
+        Ok(())
+    }
+
+    /// Loads the archived days of every year from `start` to `end` that is
+    /// not loaded yet.
+    pub fn load_range(&mut self, start: NaiveDate, end: NaiveDate) -> AppResult<()> {
+        let years: Vec<i32> = self
+            .archive_years
+            .range(start.year()..=end.year())
+            .filter(|year| !self.loaded_years.contains(year))
+            .copied()
+            .collect();
+        for year in years {
+            for day in self.store.load_archive(year, self.cipher.as_ref())? {
+                // A day that is also stored outside the archive was changed
+                // after archiving, so that copy wins.
+                if let Entry::Vacant(entry) = self.days.entry(day.date) {
+                    self.archived.insert(day.date);
+                    entry.insert(day);
+                }
+            }
+            self.loaded_years.insert(year);
+        }
+        Ok(())
+    }
+
+    /// Years with archived days.
+    pub fn archived_years(&self) -> &BTreeSet<i32> {
+        &self.archive_years
+    }
+
+    /// Moves the days from `start` to `end` into compressed yearly archives,
+    /// which are only loaded again when those dates are needed. Returns the
+    /// number of days archived.
+    pub fn archive(&mut self, start: NaiveDate, end: NaiveDate) -> AppResult<usize> {
+        let _lock = self.store.lock(LockMode::Exclusive)?;
+        self.sync()?;
+        self.load_range(start, end)?;
+        let dates: Vec<NaiveDate> = self
+            .days
+            .range(start..=end)
+            .map(|(&date, _)| date)
+            .filter(|date| !self.archived.contains(date))
+            .collect();
+        for date in &dates {
+            self.archived.insert(*date);
+            self.archive_years.insert(date.year());
+            self.loaded_years.insert(date.year());
+        }
+        self.save_snapshot(true)?;
+        self.unload_archives();
+        Ok(dates.len())
+    }
+
+    /// Moves archived days from `start` to `end` back into the regular data
+    /// files. Returns the number of days restored.
+    pub fn unarchive(&mut self, start: NaiveDate, end: NaiveDate) -> AppResult<usize> {
+        let _lock = self.store.lock(LockMode::Exclusive)?;
+        self.sync()?;
+        self.load_range(start, end)?;
+        let dates: Vec<NaiveDate> = self.archived.range(start..=end).copied().collect();
+        for date in &dates {
+            self.archived.remove(date);
+        }
+        self.save_snapshot(true)?;
+        self.archive_years = self.store.archived_years()?;
+        Ok(dates.len())
+    }
+
+    /// Drops saved archived days from memory, except for the current year.
+    fn unload_archives(&mut self) {
+        let keep = self.current_date.year();
+        let archived = &self.archived;
+        self.days
+            .retain(|date, _| date.year() == keep || !archived.contains(date));
+        self.archived.retain(|date| date.year() == keep);
+        self.loaded_years.retain(|&year| year == keep);
+    }
+
+    /// Loads every archive, for operations that rewrite all data files.
+    fn load_all(&mut self) -> AppResult<()> {
+        self.load_range(NaiveDate::MIN, NaiveDate::MAX)
+    }
+
+    /// Makes `date` the current day, or the closest earlier day if it does
+    /// not exist, keeping at least one day around.
+    fn select_day(&mut self, date: NaiveDate) {
+        if self.days.is_empty() {
+            let today = Local::now().date_naive();
+            self.days.insert(today, Day::new(today));
+        }
+        self.current_date = self
+            .days
+            .range(..=date)
+            .next_back()
+            .or_else(|| self.days.iter().next())
+            .map(|(&date, _)| date)
+            .unwrap_or(date);
+    }
+
+    /// Checks the recorded days for duplicate dates, ordering problems and
+    /// invalid entries. Duplicates and ordering are reported as found in the
+    /// data file; they are resolved in memory and fixed on disk by `repair`.
+    pub fn check(&self) -> Report {
+        let mut report = Report {
+            problems: self
+                .load_report
+                .problems
+                .iter()
+                .filter(|problem| {
+                    matches!(
+                        problem,
+                        Problem::DuplicateDate(_) | Problem::Unsorted { .. }
+                    )
+                })
+                .cloned()
+                .collect(),
+        };
+        report
+            .problems
+            .extend(self.days.values().flat_map(validate_day));
+        report
+    }
+
+    /// Fixes every problem reported by `check` and saves the result. Returns
+    /// the problems that were fixed.
+    pub fn repair(&mut self) -> AppResult<Report> {
+        let _lock = self.store.lock(LockMode::Exclusive)?;
+        self.sync()?;
+        self.load_all()?;
+        let report = self.check();
+        if !report.is_ok() {
+            let change = Change::Repaired {
+                days: repair(self.days.values().cloned().collect()),
+            };
+            change.apply(&mut self.days);
+            self.journal.append(change)?;
+            self.load_report = Report::default();
+            self.select_day(self.current_date);
+            self.save_snapshot(true)?;
+        }
+        Ok(report)
+    }
+
+    /// Writes the current days as a snapshot so that loading does not have to
+    /// replay the journal from the start. Unless `all` is set, only days
+    /// changed since the last snapshot are rewritten where the layout allows
+    /// it. The caller must hold the file lock.
+    fn save_snapshot(&mut self, all: bool) -> AppResult<()> {
+        if let Some(problem) = self.check().problems.first() {
+            return Err(format!("Refusing to save invalid data: {}", problem).into());
+        }
+        // Archives are written first, so that the snapshot never points past
+        // changes to archived days that were not saved.
+        let years: BTreeSet<i32> = if all {
+            self.loaded_years.clone()
+        } else {
+            self.unsaved_dates
+                .intersection(&self.archived)
+                .map(|date| date.year())
+                .collect()
+        };
+        for year in years {
+            let days: Vec<&Day> = self
+                .archived
+                .iter()
+                .filter(|date| date.year() == year)
+                .filter_map(|date| self.days.get(date))
+                .collect();
+            self.store.save_archive(year, &days, self.cipher.as_ref())?;
+        }
+        self.store.save_snapshot(
+            self.journal.seq(),
+            self.journal.offset(),
+            &self.days,
+            &self.archived,
+            if all { None } else { Some(&self.unsaved_dates) },
+            self.cipher.as_ref(),
+        )?;
+        self.events_since_snapshot = 0;
+        self.unsaved_dates.clear();
+        Ok(())
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.store.layout()
+    }
+
+    /// Copies the data set to `path` with the given layout and continues
+    /// working from there. The original files are left in place.
+    pub fn convert(&mut self, path: &str, layout: Layout) -> AppResult<()> {
+        let _lock = self.store.lock(LockMode::Exclusive)?;
+        self.sync()?;
+        self.load_all()?;
+
+        let store = Store::with_layout(path, layout);
+        if store.exists() {
+            return Err(format!("{} already contains data", path).into());
+        }
+        store.create()?;
+        let _target_lock = store.lock(LockMode::Exclusive)?;
+        let cipher = self.cipher.clone();
+        let offset = self.write_copy(&store, cipher.as_ref())?;
+        self.products().copy_to(&store.products_dir())?;
+        self.cached_products()
+            .copy_to(&store.products_cache_dir())?;
+        self.journal = Journal::open(&store.journal_path(), offset, self.journal.seq(), cipher);
+        self.api_cache
+            .set_location(&store.api_cache_path(), self.cipher.as_ref());
+        self.store = store;
+        self.events_since_snapshot = 0;
+        self.unsaved_dates.clear();
+        Ok(())
+    }
+
+    /// Writes the whole data set to `store`, encrypted with `cipher`, without
+    /// touching the files in use: journal, undo history, profile, library,
+    /// provider cache, archives and snapshot. Every archive must be loaded.
+    /// Returns the journal offset of the copy.
+    fn write_copy(&mut self, store: &Store, cipher: Option<&Cipher>) -> AppResult<u64> {
+        if let Some(problem) = self.check().problems.first() {
+            return Err(format!("Refusing to save invalid data: {}", problem).into());
+        }
+        let events = journal::read_all(&self.store.journal_path(), self.cipher.as_ref())?;
+        store.create()?;
+        let offset = journal::rewrite(&store.journal_path(), &events, cipher)?;
+        save_history(&store.history_path(), &self.history, cipher)?;
+        self.save_profile(store, cipher)?;
+        let library = self.library()?;
+        if !library.is_empty() {
+            library.save(&store.library_path(), cipher)?;
+        }
+        self.api_cache.write_to(&store.api_cache_path(), cipher)?;
+        for &year in &self.loaded_years {
+            let days: Vec<&Day> = self
+                .archived
+                .iter()
+                .filter(|date| date.year() == year)
+                .filter_map(|date| self.days.get(date))
+                .collect();
+            store.save_archive(year, &days, cipher)?;
         }
+        store.save_snapshot(
+            self.journal.seq(),
+            offset,
+            &self.days,
+            &self.archived,
+            None,
+            cipher,
+        )?;
+        Ok(offset)
+    }
+
+    fn save_profile(&self, store: &Store, cipher: Option<&Cipher>) -> AppResult<()> {
+        let serialized = serde_json::to_string_pretty(&self.profile)?;
+        write_data(&store.profile_path(), serialized.as_bytes(), cipher)
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Applies `config`, rebuilding the HTTP client and providers.
+    pub fn set_config(&mut self, config: Config) -> AppResult<()> {
+        self.http = HttpClient::new(&config.http)?;
+        self.providers = api::providers(&config, &self.http);
+        self.api_cache.set_max_age(config.cache_max_age());
+        self.config = config;
+        Ok(())
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    /// Re-writes the data file, journal and undo history encrypted with
+    /// `passphrase`, or unencrypted when `None`. This encrypts, decrypts or
+    /// changes the passphrase of the data file. The files are only replaced
+    /// once all of them were written, so a failure leaves the data as it
+    /// was. Other running instances must be restarted afterwards.
+    pub fn set_passphrase(&mut self, passphrase: Option<&str>) -> AppResult<()> {
+        let _lock = self.store.lock(LockMode::Exclusive)?;
+        self.sync()?;
+        self.load_all()?;
+        let cipher = passphrase.map(Cipher::new).transpose()?;
+
+        let staging = self.store.staging();
+        // Left over from an attempt that was interrupted.
+        staging.discard()?;
+        let offset = match self.write_copy(&staging, cipher.as_ref()) {
+            Ok(offset) => offset,
+            Err(e) => {
+                let _ = staging.discard();
+                return Err(e);
+            }
+        };
+        self.store.replace_with(&staging)?;
+
+        let path = self.store.journal_path();
+        self.journal = Journal::open(&path, offset, self.journal.seq(), cipher.clone());
+        self.api_cache
+            .set_location(&self.store.api_cache_path(), cipher.as_ref());
+        self.cipher = cipher;
+        self.events_since_snapshot = 0;
+        self.unsaved_dates.clear();
         Ok(())
     }
-    pub fn set_user_info(&mut self, height: f32, weight: f32, age: u32, gender: Gender) {
-        self.user_height = height;
-        self.user_weight = weight;
-        self.user_age = age;
-        self.user_gender = gender;
+
+    /// Returns every recorded event, oldest first.
+    pub fn events(&self) -> AppResult<Vec<Event>> {
+        journal::read_all(&self.store.journal_path(), self.cipher.as_ref())
+    }
+
+    /// Rebuilds the days as they were at the given point in time.
+    pub fn days_at(&self, at: DateTime<Local>) -> AppResult<Days> {
+        journal::replay_until(&self.store.journal_path(), at, self.cipher.as_ref())
+    }
+
+    /// Reverts the most recent change. Returns its description, or `None` if
+    /// there is nothing to undo.
+    ///
+    /// The history is shared by all instances, so this may undo a change
+    /// made by another one. If the day no longer looks as the change left
+    /// it, the undo is refused.
+    pub fn undo(&mut self) -> AppResult<Option<String>> {
+        let _lock = self.store.lock(LockMode::Exclusive)?;
+        self.sync()?;
+        let Some(action) = self.history.next_undo().cloned() else {
+            return Ok(None);
+        };
+        let change = Change::DayRestored {
+            date: action.date,
+            day: action.before,
+        };
+        // Only taken off the stack once the change is saved, so a failed
+        // undo can be retried.
+        self.commit_synced(change, Some(Seen::Day(action.after)), |history, _, _, _| {
+            history.undo();
+        })?;
+        Ok(Some(action.description))
+    }
+
+    /// Re-applies the most recently undone change. Returns its description, or
+    /// `None` if there is nothing to redo.
+    pub fn redo(&mut self) -> AppResult<Option<String>> {
+        let _lock = self.store.lock(LockMode::Exclusive)?;
+        self.sync()?;
+        let Some(action) = self.history.next_redo().cloned() else {
+            return Ok(None);
+        };
+        let change = Change::DayRestored {
+            date: action.date,
+            day: action.after,
+        };
+        self.commit_synced(
+            change,
+            Some(Seen::Day(action.before)),
+            |history, _, _, _| {
+                history.redo();
+            },
+        )?;
+        Ok(Some(action.description))
+    }
+
+    pub fn next_undo(&self) -> Option<&str> {
+        self.history.next_undo().map(|a| a.description.as_str())
+    }
+
+    pub fn next_redo(&self) -> Option<&str> {
+        self.history.next_redo().map(|a| a.description.as_str())
+    }
+
+    pub fn reset_day(&mut self) -> AppResult<()> {
+        let date = self.get_current_day()?.date;
+        self.commit(Change::DayReset { date }, Some("Reset day"))
+    }
+    pub fn set_user_info(
+        &mut self,
+        height: f32,
+        weight: f32,
+        age: u32,
+        gender: Gender,
+    ) -> AppResult<()> {
+        self.profile = Profile {
+            height,
+            weight,
+            age,
+            gender,
+        };
+        let _lock = self.store.lock(LockMode::Exclusive)?;
+        self.save_profile(&self.store, self.cipher.as_ref())
     }
     pub fn add_workout(&mut self, workout: Workout) -> AppResult<()> {
-        self.get_current_day_mut()?.add_workout(workout);
-        self.save()
+        let date = self.get_current_day()?.date;
+        let description = format!("Add {}", workout.label());
+        self.commit(Change::WorkoutAdded { date, workout }, Some(&description))
+    }
+
+    pub fn remove_workout(&mut self, index: usize) -> AppResult<()> {
+        let day = self.get_current_day()?;
+        let date = day.date;
+        let Some(workout) = day.workouts.get(index) else {
+            return Ok(());
+        };
+        let description = format!("Remove {}", workout.label());
+        let seen = Seen::Workout(workout.clone());
+        self.commit_entry(Change::WorkoutRemoved { date, index }, seen, &description)
+    }
+
+    /// Replaces the workout at `index` on `date`.
+    pub fn edit_workout(
+        &mut self,
+        date: NaiveDate,
+        index: usize,
+        workout: Workout,
+    ) -> AppResult<()> {
+        self.load_range(date, date)?;
+        let Some(old) = self.days.get(&date).and_then(|day| day.workouts.get(index)) else {
+            return Err(format!("No workout #{} on {}", index + 1, date).into());
+        };
+        let description = format!("Edit {}", old.label());
+        let seen = Seen::Workout(old.clone());
+        let change = Change::WorkoutEdited {
+            date,
+            index,
+            workout,
+        };
+        self.commit_entry(change, seen, &description)
     }
 
-    pub fn get_week_calories_and_workouts(&self) -> Vec<(NaiveDate, f64, Option<&Workout>)> {
+    /// Asks Nutritionix what the exercises described in `query` burnt for
+    /// someone of the profile's weight, height and age, without logging
+    /// them. Fails with `ApiError::NotConfigured` without Nutritionix
+    /// credentials.
+    pub fn search_exercise(&self, query: &str) -> AppResult<Vec<Workout>> {
+        let exercises = self
+            .runtime
+            .block_on(api::search_exercise(query, &self.profile, &self.providers))
+            .map_err(|e| self.explain(e))?;
+        Ok(exercises.iter().map(|e| e.to_workout()).collect())
+    }
+
+    pub fn get_week_calories_and_workouts(&self) -> Vec<(NaiveDate, f64, &[Workout])> {
         let current_date = self.get_current_day().unwrap().date;
         let week_start = current_date - chrono::Duration::days(6);
 
         (0..7)
             .map(|i| {
                 let date = week_start + chrono::Duration::days(i);
-                let day = self.days.iter().find(|day| day.date == date);
+                let day = self.days.get(&date);
                 let calories = day.map(|d| d.total_calories()).unwrap_or(0.0);
-                let workout = day.and_then(|d| d.workout.as_ref());
-                (date, calories, workout)
+                let workouts = day.map_or(&[][..], |d| &d.workouts);
+                (date, calories, workouts)
             })
             .collect()
     }
 
     pub fn calculate_bmi(&self) -> f32 {
-        let height_in_meters = self.user_height / 100.0;
-        self.user_weight / (height_in_meters * height_in_meters)
+        let height_in_meters = self.profile.height / 100.0;
+        self.profile.weight / (height_in_meters * height_in_meters)
     }
 
     pub fn calculate_bmr(&self) -> f32 {
-        match self.user_gender {
+        match self.profile.gender {
             Gender::Male => {
-                88.362 + (13.397 * self.user_weight) + (4.799 * self.user_height)
-                    - (5.677 * self.user_age as f32)
+                88.362 + (13.397 * self.profile.weight) + (4.799 * self.profile.height)
+                    - (5.677 * self.profile.age as f32)
             }
             Gender::Female => {
-                447.593 + (9.247 * self.user_weight) + (3.098 * self.user_height)
-                    - (4.330 * self.user_age as f32)
+                447.593 + (9.247 * self.profile.weight) + (3.098 * self.profile.height)
+                    - (4.330 * self.profile.age as f32)
             }
         }
     }
 }
 
+/// Position of `seen` in `entries`: `index` if it is still there, or where
+/// it moved to.
+/// Whether `day` is `expected`, counting a day with nothing recorded the same
+/// as no day at all.
+fn same_day(day: Option<&Day>, expected: Option<&Day>) -> bool {
+    let blank =
+        |day: Option<&Day>| day.is_none_or(|day| day.foods.is_empty() && day.workouts.is_empty());
+    match (day, expected) {
+        (Some(day), Some(expected)) => day == expected,
+        _ => blank(day) && blank(expected),
+    }
+}
+
+fn find_entry<T: PartialEq>(entries: &[T], index: usize, seen: &T) -> Option<usize> {
+    if entries.get(index) == Some(seen) {
+        Some(index)
+    } else {
+        entries.iter().position(|entry| entry == seen)
+    }
+}
+
 impl Workout {
     pub fn new(workout_type: WorkoutType, duration: u32) -> Self {
         let calories_burnt = match workout_type {
@@ -252,12 +1202,22 @@ impl Workout {
         };
 
         Self {
+            name: None,
             workout_type,
             duration,
             calories_burnt,
         }
     }
 
+    /// The name, or the workout type for workouts without one.
+    pub fn label(&self) -> String {
+        match (&self.name, self.workout_type) {
+            (Some(name), _) => name.clone(),
+            (None, WorkoutType::WeightLifting) => "Weight Lifting".to_string(),
+            (None, WorkoutType::Cardio) => "Cardio".to_string(),
+        }
+    }
+
     pub fn set_cardio_calories(&mut self, calories: u32) {
         if self.workout_type == WorkoutType::Cardio {
             self.calories_burnt = calories;
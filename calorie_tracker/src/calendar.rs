@@ -0,0 +1,144 @@
+use crate::RawMode;
+use calorie_tracker::{App, AppResult};
+use chrono::{Datelike, Duration, Local, Months, NaiveDate};
+use colored::*;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, queue};
+use dialoguer::{theme::ColorfulTheme, Input};
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+const HELP: &str = "←→ day  ↑↓ week  PgUp/PgDn month  t today  Enter pick  Esc cancel";
+
+/// Lets the user pick a date on a month calendar, starting at `date`. Days
+/// with foods or workouts logged are highlighted. Returns `None` when
+/// cancelled. Falls back to a plain prompt when the terminal does not
+/// support raw mode.
+pub fn pick_date(app: &mut App, date: NaiveDate) -> AppResult<Option<NaiveDate>> {
+    let _raw = match RawMode::enable() {
+        Ok(raw) => raw,
+        Err(_) => {
+            let date: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt("Enter date (YYYY-MM-DD)")
+                .default(date.to_string())
+                .validate_with(|input: &String| -> Result<(), &str> {
+                    match NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+                        Ok(_) => Ok(()),
+                        Err(_) => Err("Use YYYY-MM-DD, e.g. 2026-10-16"),
+                    }
+                })
+                .interact_text()?;
+            return Ok(Some(NaiveDate::parse_from_str(&date, "%Y-%m-%d")?));
+        }
+    };
+
+    let mut stdout = io::stdout();
+    let mut selected = date;
+    let mut marked = BTreeSet::new();
+    let mut month = None;
+    let picked = loop {
+        let first = first_of_month(selected);
+        if month != Some(first) {
+            marked = app.dates_with_data(first, last_of_month(selected))?;
+            month = Some(first);
+        }
+        draw(&mut stdout, selected, &marked)?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        let moved = match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => break None,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break None,
+            KeyCode::Enter => break Some(selected),
+            KeyCode::Left | KeyCode::Char('h') => selected.checked_sub_signed(Duration::days(1)),
+            KeyCode::Right | KeyCode::Char('l') => selected.checked_add_signed(Duration::days(1)),
+            KeyCode::Up | KeyCode::Char('k') => selected.checked_sub_signed(Duration::days(7)),
+            KeyCode::Down | KeyCode::Char('j') => selected.checked_add_signed(Duration::days(7)),
+            KeyCode::PageUp | KeyCode::Char('[') => selected.checked_sub_months(Months::new(1)),
+            KeyCode::PageDown | KeyCode::Char(']') => selected.checked_add_months(Months::new(1)),
+            KeyCode::Char('t') => Some(Local::now().date_naive()),
+            _ => None,
+        };
+        if let Some(date) = moved {
+            selected = date;
+        }
+    };
+
+    queue!(
+        stdout,
+        cursor::MoveToColumn(0),
+        terminal::Clear(ClearType::FromCursorDown)
+    )?;
+    stdout.flush()?;
+    Ok(picked)
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+fn last_of_month(date: NaiveDate) -> NaiveDate {
+    first_of_month(date)
+        .checked_add_months(Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .unwrap_or(date)
+}
+
+/// Redraws the month of `selected`, leaving the cursor on its first line.
+fn draw(
+    stdout: &mut io::Stdout,
+    selected: NaiveDate,
+    marked: &BTreeSet<NaiveDate>,
+) -> AppResult<()> {
+    queue!(
+        stdout,
+        cursor::MoveToColumn(0),
+        terminal::Clear(ClearType::FromCursorDown)
+    )?;
+    let today = Local::now().date_naive();
+    let first = first_of_month(selected);
+    let mut lines = vec![
+        format!("{:^20}", selected.format("%B %Y").to_string())
+            .bold()
+            .to_string(),
+        "Mo Tu We Th Fr Sa Su".dimmed().to_string(),
+    ];
+    // Always six weeks, so every month takes the same number of lines.
+    let mut date = first - Duration::days(first.weekday().num_days_from_monday() as i64);
+    for _ in 0..6 {
+        let mut week = Vec::new();
+        for _ in 0..7 {
+            let mut cell = format!("{:>2}", date.day()).normal();
+            if date.month() != selected.month() {
+                cell = "  ".normal();
+            } else if marked.contains(&date) {
+                cell = cell.green().bold();
+            }
+            if date == today {
+                cell = cell.underline();
+            }
+            if date == selected {
+                cell = cell.reversed();
+            }
+            week.push(cell.to_string());
+            date += Duration::days(1);
+        }
+        lines.push(week.join(" "));
+    }
+    lines.push(format!("{} {}", "■".green(), "days with data".dimmed()));
+    lines.push(HELP.dimmed().to_string());
+
+    write!(stdout, "{}", lines.join("\r\n"))?;
+    queue!(
+        stdout,
+        cursor::MoveUp(lines.len() as u16 - 1),
+        cursor::MoveToColumn(0)
+    )?;
+    stdout.flush()?;
+    Ok(())
+}
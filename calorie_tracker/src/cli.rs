@@ -0,0 +1,608 @@
+//! Subcommands for scripting the tracker. Without one, the menus open.
+
+use crate::{doctor_command, layout_for_path, new_passphrase, print_cache_stats};
+use calorie_tracker::api::ApiError;
+use calorie_tracker::day::Day;
+use calorie_tracker::food::parse_time;
+use calorie_tracker::import::open_food_facts::Filter;
+use calorie_tracker::{App, AppResult, Food, Gender, Meal, Profile, Workout, WorkoutType};
+use chrono::{Duration, Local, NaiveDate, NaiveTime};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+use std::process::ExitCode;
+
+/// Exit status when nothing matched, such as an unknown food or entry number.
+const NOT_FOUND: u8 = 3;
+/// Exit status when a nutrition provider failed or none is configured.
+const PROVIDER_FAILED: u8 = 4;
+
+/// Tracks calories, protein and workouts. Run without a command for the
+/// interactive menus.
+///
+/// Exit status: 0 on success, 1 on errors, 2 on invalid arguments, 3 when
+/// nothing matched and 4 when a nutrition provider failed.
+#[derive(Parser)]
+#[command(name = "calorie_tracker", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Logs foods described in words, such as "2 eggs and a cup of rice"
+    Add {
+        description: String,
+        /// breakfast, lunch, dinner or snack
+        #[arg(long)]
+        meal: Option<Meal>,
+        /// Day to log on (YYYY-MM-DD); today by default
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Removes a food by its number in `day show` or by its name
+    Remove {
+        food: String,
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Changes a food, found by its number in `day show` or by its name.
+    /// A new quantity scales the nutrient values unless they are given too.
+    #[command(group(ArgGroup::new("fields").required(true).multiple(true)))]
+    Edit {
+        food: String,
+        #[arg(long, group = "fields")]
+        name: Option<String>,
+        #[arg(long, group = "fields")]
+        quantity: Option<f64>,
+        #[arg(long, group = "fields")]
+        unit: Option<String>,
+        /// Computed from the macros when only they are given
+        #[arg(long, group = "fields")]
+        calories: Option<f64>,
+        #[arg(long, group = "fields")]
+        protein: Option<f64>,
+        #[arg(long, group = "fields")]
+        fat: Option<f64>,
+        #[arg(long, group = "fields")]
+        carbs: Option<f64>,
+        /// Meal eaten at, or `none` to clear it
+        #[arg(long, group = "fields", value_parser = parse_meal_or_none)]
+        meal: Option<Clearable<Meal>>,
+        /// Time eaten (HH:MM), or `none` to clear it
+        #[arg(long, group = "fields", value_parser = parse_time_or_none)]
+        time: Option<Clearable<NaiveTime>>,
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    #[command(subcommand)]
+    Workout(WorkoutCommand),
+    #[command(subcommand)]
+    Day(DayCommand),
+    #[command(subcommand)]
+    Report(ReportCommand),
+    #[command(subcommand)]
+    Profile(ProfileCommand),
+    /// Opens the full-screen dashboard
+    Dashboard,
+    /// Checks the data for problems; fails when there are any
+    Doctor {
+        /// Repairs the problems found
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Encrypts the data, or changes the passphrase of encrypted data
+    #[command(alias = "change-passphrase")]
+    Encrypt,
+    /// Stores the data unencrypted
+    Decrypt,
+    /// Moves the data to a single file (ending in .json) or a directory with
+    /// one file per day
+    Convert { path: String },
+    /// Imports a USDA FoodData Central JSON file or CSV directory into the
+    /// food library
+    ImportUsda { path: String },
+    /// Imports an Open Food Facts dump (.jsonl.gz or .csv.gz)
+    ImportOff {
+        path: String,
+        /// Only imports products sold in this country; may be repeated
+        #[arg(long = "country")]
+        countries: Vec<String>,
+    },
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Moves the days from START to END into yearly archives
+    Archive { start: NaiveDate, end: NaiveDate },
+    /// Moves archived days from START to END back into the data
+    Unarchive { start: NaiveDate, end: NaiveDate },
+}
+
+/// Logs and removes workouts
+#[derive(Subcommand)]
+pub enum WorkoutCommand {
+    /// Logs a workout
+    Add {
+        kind: WorkoutKind,
+        #[arg(long)]
+        minutes: u32,
+        /// Calories burnt; required for cardio, estimated for weight lifting
+        #[arg(long, required_if_eq("kind", "cardio"))]
+        calories: Option<u32>,
+        /// What was done, such as "cycling"
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Logs workouts described in words, such as "ran 30 min", as estimated
+    /// by Nutritionix
+    Describe {
+        description: String,
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Removes a workout by its number in `day show`
+    Remove {
+        number: usize,
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+}
+
+/// Shows logged days
+#[derive(Subcommand)]
+pub enum DayCommand {
+    /// Shows the foods and workouts of a day
+    Show {
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Summarizes several days
+#[derive(Subcommand)]
+pub enum ReportCommand {
+    /// Calories, protein and workouts of the seven days up to a date
+    Week {
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Shows or changes the body measurements used for estimates
+#[derive(Subcommand)]
+pub enum ProfileCommand {
+    Show {
+        #[arg(long)]
+        json: bool,
+    },
+    /// Changes the given measurements and keeps the others
+    #[command(group(ArgGroup::new("fields").required(true).multiple(true)))]
+    Set {
+        /// In centimeters
+        #[arg(long, group = "fields")]
+        height: Option<f32>,
+        /// In kilograms
+        #[arg(long, group = "fields")]
+        weight: Option<f32>,
+        #[arg(long, group = "fields")]
+        age: Option<u32>,
+        #[arg(long, group = "fields")]
+        gender: Option<GenderArg>,
+    },
+}
+
+/// Manages the cache of nutrition search results
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    Stats,
+    /// Removes expired entries
+    Purge {
+        /// Removes all entries
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum WorkoutKind {
+    WeightLifting,
+    Cardio,
+}
+
+impl From<WorkoutKind> for WorkoutType {
+    fn from(kind: WorkoutKind) -> Self {
+        match kind {
+            WorkoutKind::WeightLifting => WorkoutType::WeightLifting,
+            WorkoutKind::Cardio => WorkoutType::Cardio,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum GenderArg {
+    Male,
+    Female,
+}
+
+impl From<GenderArg> for Gender {
+    fn from(gender: GenderArg) -> Self {
+        match gender {
+            GenderArg::Male => Gender::Male,
+            GenderArg::Female => Gender::Female,
+        }
+    }
+}
+
+/// Nothing matched what the command was asked to find.
+#[derive(Debug)]
+pub struct NotFound(pub String);
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for NotFound {}
+
+/// The exit status for a failed command.
+pub fn exit_code(e: &(dyn Error + Send + Sync + 'static)) -> ExitCode {
+    if e.is::<NotFound>() {
+        return ExitCode::from(NOT_FOUND);
+    }
+    match e.downcast_ref::<ApiError>() {
+        Some(ApiError::NotFound(_)) => ExitCode::from(NOT_FOUND),
+        Some(_) => ExitCode::from(PROVIDER_FAILED),
+        None => ExitCode::FAILURE,
+    }
+}
+
+/// The food `name_or_number` refers to: its number in `day show`, or its name.
+fn find_food(day: &Day, name_or_number: &str) -> Result<usize, NotFound> {
+    let index = match name_or_number.parse::<usize>() {
+        Ok(number) => number
+            .checked_sub(1)
+            .filter(|&index| index < day.foods.len()),
+        Err(_) => day
+            .foods
+            .iter()
+            .position(|f| f.name.eq_ignore_ascii_case(name_or_number)),
+    };
+    index.ok_or_else(|| NotFound(format!("No food '{}' on {}", name_or_number, day.date)))
+}
+
+/// A new value for an optional field, where `none` clears it.
+#[derive(Debug, Clone, Copy)]
+pub struct Clearable<T>(Option<T>);
+
+fn parse_meal_or_none(meal: &str) -> Result<Clearable<Meal>, String> {
+    match meal {
+        "none" => Ok(Clearable(None)),
+        meal => meal.parse().map(|meal| Clearable(Some(meal))),
+    }
+}
+
+fn parse_time_or_none(time: &str) -> Result<Clearable<NaiveTime>, String> {
+    match time {
+        "none" => Ok(Clearable(None)),
+        time => parse_time(time).map(|time| Clearable(Some(time))),
+    }
+}
+
+/// Switches to `date`, or today, creating the day if needed.
+fn go_to(app: &mut App, date: Option<NaiveDate>) -> AppResult<()> {
+    app.change_day(date.unwrap_or_else(|| Local::now().date_naive()))
+}
+
+/// The day at `date`, or today, for commands that only read it. A day with
+/// nothing recorded is shown empty rather than created.
+fn day_at(app: &mut App, date: Option<NaiveDate>) -> AppResult<Day> {
+    let date = date.unwrap_or_else(|| Local::now().date_naive());
+    app.load_range(date, date)?;
+    Ok(app
+        .days_in_range(date, date)
+        .next()
+        .cloned()
+        .unwrap_or_else(|| Day::new(date)))
+}
+
+pub fn run(app: &mut App, command: Command) -> AppResult<()> {
+    match command {
+        Command::Add {
+            description,
+            meal,
+            date,
+        } => {
+            go_to(app, date)?;
+            let foods = app.search_and_add_food(&description, meal)?;
+            if foods.is_empty() {
+                return Err(NotFound(format!("No foods recognized in '{}'", description)).into());
+            }
+            for food in &foods {
+                println!("Added {}", describe_food(food));
+            }
+        }
+        Command::Remove { food, date } => {
+            go_to(app, date)?;
+            let day = app.get_current_day()?;
+            let index = find_food(day, &food)?;
+            let name = day.foods[index].name.clone();
+            app.remove_food(index)?;
+            println!("Removed {}", name);
+        }
+        Command::Edit {
+            food,
+            name,
+            quantity,
+            unit,
+            calories,
+            protein,
+            fat,
+            carbs,
+            meal,
+            time,
+            date,
+        } => {
+            go_to(app, date)?;
+            let day = app.get_current_day()?;
+            let index = find_food(day, &food)?;
+            let old = &day.foods[index];
+            let mut edited = match quantity {
+                Some(quantity) => old.with_quantity(quantity),
+                None => old.clone(),
+            };
+            edited.name = name.unwrap_or(edited.name);
+            edited.unit = unit.unwrap_or(edited.unit);
+            edited.protein = protein.unwrap_or(edited.protein);
+            edited.fat = fat.unwrap_or(edited.fat);
+            edited.carbs = carbs.unwrap_or(edited.carbs);
+            edited.calories = match calories {
+                Some(calories) => calories,
+                None if protein.is_some() || fat.is_some() || carbs.is_some() => {
+                    edited.macro_calories()
+                }
+                None => edited.calories,
+            };
+            edited.meal = meal.map_or(edited.meal, |meal| meal.0);
+            edited.time = time.map_or(edited.time, |time| time.0);
+            let date = day.date;
+            let described = describe_food(&edited);
+            app.edit_food(date, index, edited)?;
+            println!("Changed to {}", described);
+        }
+        Command::Workout(command) => run_workout(app, command)?,
+        Command::Day(DayCommand::Show { date, json }) => show_day(&day_at(app, date)?, json)?,
+        Command::Report(ReportCommand::Week { date, json }) => {
+            let date = date.unwrap_or_else(|| Local::now().date_naive());
+            app.load_range(date - Duration::days(6), date)?;
+            show_week(app, date, json)?;
+        }
+        Command::Profile(ProfileCommand::Show { json }) => show_profile(app, json)?,
+        Command::Profile(ProfileCommand::Set {
+            height,
+            weight,
+            age,
+            gender,
+        }) => {
+            let profile = app.profile().clone();
+            app.set_user_info(
+                height.unwrap_or(profile.height),
+                weight.unwrap_or(profile.weight),
+                age.unwrap_or(profile.age),
+                gender.map_or(profile.gender, Gender::from),
+            )?;
+            show_profile(app, false)?;
+        }
+        Command::Dashboard => crate::tui::run(app)?,
+        Command::Doctor { fix } => doctor_command(app, fix)?,
+        Command::Encrypt => app.set_passphrase(Some(&new_passphrase()?))?,
+        Command::Decrypt => app.set_passphrase(None)?,
+        Command::Convert { path } => app.convert(&path, layout_for_path(&path))?,
+        Command::ImportUsda { path } => {
+            println!("Imported {} foods.", app.import_usda(&path)?);
+        }
+        Command::ImportOff { path, countries } => {
+            let count = app.import_open_food_facts(&path, &Filter { countries })?;
+            println!("Imported {} products.", count);
+        }
+        Command::Cache(CacheCommand::Stats) => print_cache_stats(app),
+        Command::Cache(CacheCommand::Purge { all }) => {
+            println!("Removed {} cache entries.", app.purge_cache(all)?);
+        }
+        Command::Archive { start, end } => println!("Archived {} days.", app.archive(start, end)?),
+        Command::Unarchive { start, end } => {
+            println!("Unarchived {} days.", app.unarchive(start, end)?);
+        }
+    }
+    Ok(())
+}
+
+fn run_workout(app: &mut App, command: WorkoutCommand) -> AppResult<()> {
+    match command {
+        WorkoutCommand::Add {
+            kind,
+            minutes,
+            calories,
+            name,
+            date,
+        } => {
+            go_to(app, date)?;
+            let mut workout = Workout::new(kind.into(), minutes);
+            if let Some(calories) = calories {
+                workout.calories_burnt = calories;
+            }
+            workout.name = name;
+            let added = describe_workout(&workout);
+            app.add_workout(workout)?;
+            println!("Added {}", added);
+        }
+        WorkoutCommand::Describe { description, date } => {
+            go_to(app, date)?;
+            let workouts = app.search_exercise(&description)?;
+            if workouts.is_empty() {
+                return Err(
+                    NotFound(format!("No exercise recognized in '{}'", description)).into(),
+                );
+            }
+            for workout in workouts {
+                let added = describe_workout(&workout);
+                app.add_workout(workout)?;
+                println!("Added {}", added);
+            }
+        }
+        WorkoutCommand::Remove { number, date } => {
+            go_to(app, date)?;
+            let day = app.get_current_day()?;
+            let index = number
+                .checked_sub(1)
+                .filter(|&index| index < day.workouts.len())
+                .ok_or_else(|| NotFound(format!("No workout #{} on {}", number, day.date)))?;
+            let label = day.workouts[index].label();
+            app.remove_workout(index)?;
+            println!("Removed {}", label);
+        }
+    }
+    Ok(())
+}
+
+fn describe_food(food: &Food) -> String {
+    let occasion = food
+        .occasion()
+        .map(|occasion| format!(" ({})", occasion))
+        .unwrap_or_default();
+    format!(
+        "{} {} {}{}: {:.0} kcal, {:.1} g protein, {:.1} g fat, {:.1} g carbs",
+        food.quantity,
+        food.unit,
+        food.name,
+        occasion,
+        food.calories,
+        food.protein,
+        food.fat,
+        food.carbs
+    )
+}
+
+fn describe_workout(workout: &Workout) -> String {
+    format!(
+        "{}: {} min, {} kcal",
+        workout.label(),
+        workout.duration,
+        workout.calories_burnt
+    )
+}
+
+#[derive(Serialize)]
+struct DaySummary<'a> {
+    #[serde(flatten)]
+    day: &'a Day,
+    total_calories: f64,
+    total_protein: f64,
+    calories_burnt: u32,
+}
+
+fn show_day(day: &Day, json: bool) -> AppResult<()> {
+    if json {
+        let summary = DaySummary {
+            day,
+            total_calories: day.total_calories(),
+            total_protein: day.total_protein(),
+            calories_burnt: day.calories_burnt(),
+        };
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+    println!("{}", day.date);
+    println!("Foods:");
+    for (i, food) in day.foods.iter().enumerate() {
+        println!("  {}. {}", i + 1, describe_food(food));
+    }
+    println!("Workouts:");
+    for (i, workout) in day.workouts.iter().enumerate() {
+        println!("  {}. {}", i + 1, describe_workout(workout));
+    }
+    println!(
+        "Total: {:.0} kcal eaten, {:.1} g protein, {} kcal burnt",
+        day.total_calories(),
+        day.total_protein(),
+        day.calories_burnt()
+    );
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct WeekDay<'a> {
+    date: NaiveDate,
+    calories: u32,
+    protein: f32,
+    calories_burnt: u32,
+    /// Calories eaten minus those burnt and the basal metabolic rate.
+    net_calories: i32,
+    workouts: &'a [Workout],
+}
+
+fn show_week(app: &App, date: NaiveDate, json: bool) -> AppResult<()> {
+    let bmr = app.calculate_bmr();
+    let week: Vec<WeekDay> = app
+        .week_ending(date)
+        .into_iter()
+        .map(|(date, calories, protein, workouts)| {
+            let calories_burnt = workouts.iter().map(|w| w.calories_burnt).sum();
+            WeekDay {
+                date,
+                calories,
+                protein,
+                calories_burnt,
+                net_calories: calories as i32 - calories_burnt as i32 - bmr as i32,
+                workouts,
+            }
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&week)?);
+        return Ok(());
+    }
+    for day in &week {
+        println!(
+            "{}  {:>5} kcal  {:>6.1} g protein  {:>4} kcal burnt  net {:+}",
+            day.date, day.calories, day.protein, day.calories_burnt, day.net_calories
+        );
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct ProfileSummary<'a> {
+    #[serde(flatten)]
+    profile: &'a Profile,
+    bmi: f32,
+    bmr: f32,
+}
+
+fn show_profile(app: &App, json: bool) -> AppResult<()> {
+    let summary = ProfileSummary {
+        profile: app.profile(),
+        bmi: app.calculate_bmi(),
+        bmr: app.calculate_bmr(),
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+    let profile = summary.profile;
+    println!("Height: {} cm", profile.height);
+    println!("Weight: {} kg", profile.weight);
+    println!("Age: {}", profile.age);
+    println!("Gender: {:?}", profile.gender);
+    println!("BMI: {:.1}", summary.bmi);
+    println!("BMR: {:.0} kcal", summary.bmr);
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+
+/// Nutritionix application ID and key, from https://developer.nutritionix.com.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Credentials {
+    pub app_id: String,
+    pub app_key: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Provider {
+    Nutritionix,
+    Usda,
+    OpenFoodFacts,
+}
+
+impl Provider {
+    pub const ALL: [Provider; 3] = [
+        Provider::Nutritionix,
+        Provider::Usda,
+        Provider::OpenFoodFacts,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Provider::Nutritionix => "Nutritionix",
+            Provider::Usda => "USDA FoodData Central",
+            Provider::OpenFoodFacts => "Open Food Facts",
+        }
+    }
+}
+
+/// Timeouts and retries for requests to nutrition providers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    /// Time allowed for a whole request, in seconds.
+    pub timeout_secs: u64,
+    pub connect_timeout_secs: u64,
+    /// Retries of requests that failed for a transient reason.
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each further one.
+    pub backoff_ms: u64,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 15,
+            connect_timeout_secs: 5,
+            retries: 3,
+            backoff_ms: 500,
+        }
+    }
+}
+
+/// User settings read from a JSON config file, for example:
+///
+/// ```json
+/// {
+///   "nutritionix": { "app_id": "...", "app_key": "..." },
+///   "usda_api_key": "...",
+///   "providers": ["nutritionix", "usda", "open_food_facts"],
+///   "cache_days": 30,
+///   "http": { "timeout_secs": 15, "retries": 3 }
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub nutritionix: Option<Credentials>,
+    pub usda_api_key: Option<String>,
+    /// Nutrition providers in the order they are tried.
+    pub providers: Vec<Provider>,
+    /// Days before cached provider results are fetched again.
+    pub cache_days: u32,
+    pub http: HttpSettings,
+    /// Replacement addresses of provider APIs, such as a proxy or a local
+    /// test server.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub base_urls: HashMap<Provider, String>,
+    /// Why the environment's Nutritionix credentials were ignored, reported
+    /// when a provider is needed.
+    #[serde(skip)]
+    pub env_problem: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            nutritionix: None,
+            usda_api_key: None,
+            providers: vec![Provider::Nutritionix, Provider::Usda],
+            cache_days: 30,
+            http: HttpSettings::default(),
+            base_urls: HashMap::new(),
+            env_problem: None,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file at `path`, which may be missing, and applies the
+    /// environment overrides.
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Ok(Self::read(path)?.with_env())
+    }
+
+    /// Reads the config file at `path` as it is, for editing and saving back.
+    pub fn read(path: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        match fs::read(path) {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)
+                .map_err(|e| format!("Invalid config file {}: {}", path, e))?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The default config with the environment overrides applied.
+    pub fn from_env() -> Self {
+        Config::default().with_env()
+    }
+
+    /// Takes the Nutritionix credentials from `NUTRITIONIX_APP_ID` and
+    /// `NUTRITIONIX_APP_KEY`, and the USDA key from `USDA_API_KEY`. When only
+    /// one of the Nutritionix variables is set, both are ignored and the
+    /// problem is kept in `env_problem`.
+    pub fn with_env(mut self) -> Self {
+        match (
+            std::env::var("NUTRITIONIX_APP_ID"),
+            std::env::var("NUTRITIONIX_APP_KEY"),
+        ) {
+            (Ok(app_id), Ok(app_key)) => {
+                self.nutritionix = Some(Credentials { app_id, app_key });
+            }
+            (Ok(_), Err(_)) => {
+                self.env_problem = Some(
+                    "NUTRITIONIX_APP_ID is set but NUTRITIONIX_APP_KEY is not; set both or neither"
+                        .to_string(),
+                );
+            }
+            (Err(_), Ok(_)) => {
+                self.env_problem = Some(
+                    "NUTRITIONIX_APP_KEY is set but NUTRITIONIX_APP_ID is not; set both or neither"
+                        .to_string(),
+                );
+            }
+            (Err(_), Err(_)) => {}
+        }
+        if let Ok(api_key) = std::env::var("USDA_API_KEY") {
+            self.usda_api_key = Some(api_key);
+        }
+        self
+    }
+
+    /// Whether the credentials `provider` needs are set.
+    pub fn is_configured(&self, provider: Provider) -> bool {
+        match provider {
+            Provider::Nutritionix => self
+                .nutritionix
+                .as_ref()
+                .is_some_and(|c| !c.app_id.is_empty() && !c.app_key.is_empty()),
+            Provider::Usda => self
+                .usda_api_key
+                .as_ref()
+                .is_some_and(|key| !key.is_empty()),
+            Provider::OpenFoodFacts => true,
+        }
+    }
+
+    pub fn cache_max_age(&self) -> chrono::Duration {
+        chrono::Duration::days(self.cache_days.into())
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
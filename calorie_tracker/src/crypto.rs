@@ -0,0 +1,94 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::error::Error;
+
+const MAGIC: &[u8] = b"CTENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN + NONCE_LEN;
+
+/// Passphrase-derived key for AES-256-GCM.
+///
+/// Every encrypted blob is laid out as `MAGIC | salt | nonce | ciphertext`. All
+/// files of one data set share the salt, so the key is derived only once.
+#[derive(Clone)]
+pub struct Cipher {
+    key: Key<Aes256Gcm>,
+    salt: [u8; SALT_LEN],
+}
+
+impl Cipher {
+    /// Derives a key from `passphrase` with a fresh random salt.
+    pub fn new(passphrase: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::with_salt(passphrase, salt)
+    }
+
+    /// Derives the key for data previously encrypted as `blob`.
+    pub fn for_blob(passphrase: &str, blob: &[u8]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let salt = salt_of(blob).ok_or("Data is not encrypted")?;
+        Self::with_salt(passphrase, salt)
+    }
+
+    fn with_salt(
+        passphrase: &str,
+        salt: [u8; SALT_LEN],
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut key = Key::<Aes256Gcm>::default();
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| format!("Key derivation failed: {}", e))?;
+        Ok(Self { key, salt })
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = Aes256Gcm::new(&self.key)
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| "Encryption failed")?;
+
+        let mut blob = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        blob.extend_from_slice(MAGIC);
+        blob.extend_from_slice(&self.salt);
+        blob.extend_from_slice(&nonce);
+        blob.extend_from_slice(&ciphertext);
+        Ok(blob)
+    }
+
+    pub fn decrypt(&self, blob: &[u8]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        if salt_of(blob) != Some(self.salt) {
+            return Err("Data was encrypted with a different key".into());
+        }
+        let nonce = Nonce::from_slice(&blob[MAGIC.len() + SALT_LEN..HEADER_LEN]);
+        Aes256Gcm::new(&self.key)
+            .decrypt(nonce, &blob[HEADER_LEN..])
+            .map_err(|_| "Wrong passphrase or corrupted data".into())
+    }
+
+    /// Encrypts a single line of text, e.g. a journal entry, as base64.
+    pub fn encrypt_line(&self, line: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        Ok(BASE64.encode(self.encrypt(line.as_bytes())?))
+    }
+
+    pub fn decrypt_line(&self, line: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let blob = BASE64.decode(line.trim_end())?;
+        Ok(String::from_utf8(self.decrypt(&blob)?)?)
+    }
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+fn salt_of(blob: &[u8]) -> Option<[u8; SALT_LEN]> {
+    if blob.len() < HEADER_LEN || !is_encrypted(blob) {
+        return None;
+    }
+    blob[MAGIC.len()..MAGIC.len() + SALT_LEN].try_into().ok()
+}
@@ -1,13 +1,47 @@
 use crate::app::Workout;
 use crate::food::Food;
 use chrono::NaiveDate;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Recorded days keyed by date, so iteration is chronological.
+pub type Days = BTreeMap<NaiveDate, Day>;
+
+/// Indexes a list of days by date, merging days that share a date.
+pub fn index_days(list: Vec<Day>) -> Days {
+    let mut days = Days::new();
+    for day in list {
+        match days.entry(day.date) {
+            Entry::Occupied(mut entry) => entry.get_mut().merge(day),
+            Entry::Vacant(entry) => {
+                entry.insert(day);
+            }
+        }
+    }
+    days
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Day {
     pub date: NaiveDate,
     pub foods: Vec<Food>,
-    pub workout: Option<Workout>,
+    /// Older data files hold a single, possibly null, `workout`.
+    #[serde(default, alias = "workout", deserialize_with = "one_or_many")]
+    pub workouts: Vec<Workout>,
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Workout>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        Many(Vec<Workout>),
+        One(Option<Workout>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::Many(workouts) => workouts,
+        OneOrMany::One(workout) => workout.into_iter().collect(),
+    })
 }
 
 impl Day {
@@ -15,13 +49,13 @@ impl Day {
         Self {
             date,
             foods: Vec::new(),
-            workout: None,
+            workouts: Vec::new(),
         }
     }
 
     pub fn add_food(&mut self, food: Food, quantity: f64) {
-        if let Some(existing_food) = self.foods.iter_mut().find(|f| f.name == food.name) {
-            existing_food.quantity += quantity;
+        if let Some(existing_food) = self.foods.iter_mut().find(|f| f.combines_with(&food)) {
+            existing_food.combine(&food, quantity);
         } else {
             let mut new_food = food;
             new_food.quantity = quantity;
@@ -35,16 +69,50 @@ impl Day {
         }
     }
 
+    /// Adds another record of the same date into this one. Foods are combined
+    /// as with `add_food`; workouts this day does not have yet are added.
+    pub fn merge(&mut self, other: Day) {
+        for food in other.foods {
+            let quantity = food.quantity;
+            self.add_food(food, quantity);
+        }
+        for workout in other.workouts {
+            if !self.workouts.contains(&workout) {
+                self.workouts.push(workout);
+            }
+        }
+    }
+
     pub fn add_workout(&mut self, workout: Workout) {
-        self.workout = Some(workout);
+        self.workouts.push(workout);
+    }
+
+    /// Replaces all workouts of the day with `workout`.
+    pub fn set_workout(&mut self, workout: Workout) {
+        self.workouts = vec![workout];
+    }
+
+    pub fn remove_workout(&mut self, index: usize) {
+        if index < self.workouts.len() {
+            self.workouts.remove(index);
+        }
     }
 
+    pub fn calories_burnt(&self) -> u32 {
+        self.workouts.iter().map(|w| w.calories_burnt).sum()
+    }
+
+    // Folded from 0.0 because an empty float sum is -0.0.
     pub fn total_calories(&self) -> f64 {
-        self.foods.iter().map(|food| food.calories()).sum()
+        self.foods
+            .iter()
+            .fold(0.0, |total, food| total + food.calories())
     }
 
     pub fn total_protein(&self) -> f64 {
-        self.foods.iter().map(|food| food.protein_content()).sum()
+        self.foods
+            .iter()
+            .fold(0.0, |total, food| total + food.protein_content())
     }
 
     pub fn reset(&mut self) {
@@ -53,11 +121,6 @@ impl Day {
 
     pub fn net_calories(&self, bmr: f64) -> f64 {
         let consumed = self.total_calories();
-        let burnt = self
-            .workout
-            .as_ref()
-            .map_or(0.0, |w| w.calories_burnt as f64);
-        consumed - burnt - bmr
+        consumed - self.calories_burnt() as f64 - bmr
     }
 }
-
@@ -1,6 +1,53 @@
+use chrono::NaiveTime;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The meal a food was eaten at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Meal {
+    Breakfast,
+    Lunch,
+    Dinner,
+    Snack,
+}
+
+impl fmt::Display for Meal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Meal::Breakfast => "breakfast",
+            Meal::Lunch => "lunch",
+            Meal::Dinner => "dinner",
+            Meal::Snack => "snack",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Meal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "breakfast" => Ok(Meal::Breakfast),
+            "lunch" => Ok(Meal::Lunch),
+            "dinner" => Ok(Meal::Dinner),
+            "snack" | "snacks" => Ok(Meal::Snack),
+            _ => Err(format!(
+                "unknown meal '{}', expected breakfast, lunch, dinner or snack",
+                s
+            )),
+        }
+    }
+}
+
+/// Parses a time eaten written as HH:MM.
+pub fn parse_time(time: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M")
+        .map_err(|_| format!("invalid time '{}', use HH:MM", time.trim()))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Food {
     pub name: String,
     pub quantity: f64,
@@ -9,6 +56,11 @@ pub struct Food {
     pub fat: f64,
     pub carbs: f64,
     pub calories: f64, // Keep this as f64 to maintain precision
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meal: Option<Meal>,
+    /// When it was eaten.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<NaiveTime>,
 }
 
 impl Food {
@@ -29,9 +81,67 @@ impl Food {
             fat,
             carbs,
             calories,
+            meal: None,
+            time: None,
         }
     }
 
+    /// The food with its quantity changed to `quantity` and its nutrient
+    /// values scaled to match.
+    pub fn with_quantity(&self, quantity: f64) -> Self {
+        let factor = if self.quantity > 0.0 {
+            quantity / self.quantity
+        } else {
+            1.0
+        };
+        Self {
+            quantity,
+            protein: self.protein * factor,
+            fat: self.fat * factor,
+            carbs: self.carbs * factor,
+            calories: self.calories * factor,
+            ..self.clone()
+        }
+    }
+
+    /// Whether `other` is more of this entry: the same food, meal and unit.
+    pub fn combines_with(&self, other: &Food) -> bool {
+        self.name == other.name && self.meal == other.meal && self.unit == other.unit
+    }
+
+    /// Adds another `quantity` of `other`, whose nutrient values are for
+    /// that quantity, to this entry. `other` must combine with it.
+    pub fn combine(&mut self, other: &Food, quantity: f64) {
+        debug_assert!(self.combines_with(other));
+        self.quantity += quantity;
+        self.protein += other.protein;
+        self.fat += other.fat;
+        self.carbs += other.carbs;
+        self.calories += other.calories;
+    }
+
+    pub fn with_meal(mut self, meal: Option<Meal>) -> Self {
+        self.meal = meal;
+        self
+    }
+
+    /// The meal and time, such as "breakfast at 08:30", if either is known.
+    pub fn occasion(&self) -> Option<String> {
+        let time = self.time.map(|time| time.format("%H:%M"));
+        match (self.meal, time) {
+            (Some(meal), Some(time)) => Some(format!("{} at {}", meal, time)),
+            (Some(meal), None) => Some(meal.to_string()),
+            (None, Some(time)) => Some(format!("at {}", time)),
+            (None, None) => None,
+        }
+    }
+
+    /// Calories of the macros, at 4 kcal per gram of protein and carbs and 9
+    /// per gram of fat.
+    pub fn macro_calories(&self) -> f64 {
+        self.protein * 4.0 + self.fat * 9.0 + self.carbs * 4.0
+    }
+
     pub fn calories(&self) -> f64 {
         self.calories
     }
@@ -40,4 +150,3 @@ impl Food {
         self.protein
     }
 }
-
@@ -0,0 +1,81 @@
+use crate::crypto::Cipher;
+use crate::day::Day;
+use crate::storage::{read_data, write_data};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::error::Error;
+
+/// Number of actions kept on each stack; older ones are dropped.
+pub const MAX_ACTIONS: usize = 50;
+
+/// A single mutation of one day, stored as the day before and after the change.
+/// `None` means the day did not exist on that side of the change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Action {
+    pub description: String,
+    pub date: NaiveDate,
+    pub before: Option<Day>,
+    pub after: Option<Day>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct History {
+    undo: VecDeque<Action>,
+    redo: VecDeque<Action>,
+}
+
+impl History {
+    pub fn record(&mut self, action: Action) {
+        push_bounded(&mut self.undo, action);
+        self.redo.clear();
+    }
+
+    /// Takes the most recent action off the undo stack and moves it to the redo stack.
+    pub fn undo(&mut self) -> Option<Action> {
+        let action = self.undo.pop_back()?;
+        push_bounded(&mut self.redo, action.clone());
+        Some(action)
+    }
+
+    /// Takes the most recently undone action off the redo stack and moves it back.
+    pub fn redo(&mut self) -> Option<Action> {
+        let action = self.redo.pop_back()?;
+        push_bounded(&mut self.undo, action.clone());
+        Some(action)
+    }
+
+    pub fn next_undo(&self) -> Option<&Action> {
+        self.undo.back()
+    }
+
+    pub fn next_redo(&self) -> Option<&Action> {
+        self.redo.back()
+    }
+}
+
+fn push_bounded(stack: &mut VecDeque<Action>, action: Action) {
+    stack.push_back(action);
+    while stack.len() > MAX_ACTIONS {
+        stack.pop_front();
+    }
+}
+
+pub fn load_history(
+    file_path: &str,
+    cipher: Option<&Cipher>,
+) -> Result<History, Box<dyn Error + Send + Sync>> {
+    match read_data(file_path, cipher)? {
+        Some(contents) => Ok(serde_json::from_slice(&contents)?),
+        None => Ok(History::default()),
+    }
+}
+
+pub fn save_history(
+    file_path: &str,
+    history: &History,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let serialized = serde_json::to_string(history)?;
+    write_data(file_path, serialized.as_bytes(), cipher)
+}
@@ -0,0 +1,2 @@
+pub mod open_food_facts;
+pub mod usda;
@@ -0,0 +1,211 @@
+use crate::products::Product;
+use flate2::read::MultiGzDecoder;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Which products of a dump to import.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Country names such as "germany" or tags such as "en:germany"; products
+    /// sold in any of them are kept. Empty keeps all countries.
+    pub countries: Vec<String>,
+}
+
+impl Filter {
+    fn matches(&self, countries_tags: &[String]) -> bool {
+        self.countries.is_empty()
+            || self.countries.iter().any(|country| {
+                let country = country.trim().to_lowercase().replace(' ', "-");
+                countries_tags
+                    .iter()
+                    .any(|tag| *tag == country || tag.strip_prefix("en:") == Some(&country))
+            })
+    }
+}
+
+/// Reads an Open Food Facts dump from https://world.openfoodfacts.org/data:
+/// the JSONL export (`openfoodfacts-products.jsonl.gz`) or the tab-separated
+/// CSV export (`en.openfoodfacts.org.products.csv.gz`), compressed or not.
+/// Products without a name or energy value are skipped.
+pub fn import(
+    path: &str,
+    filter: &Filter,
+    mut sink: impl FnMut(Product) -> Result<(), Box<dyn Error + Send + Sync>>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let file = File::open(path)?;
+    let name = path.strip_suffix(".gz").unwrap_or(path);
+    let reader: Box<dyn Read> = if name.len() < path.len() {
+        Box::new(MultiGzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    let reader = BufReader::with_capacity(1 << 20, reader);
+
+    let is_csv = Path::new(name)
+        .extension()
+        .is_some_and(|ext| ext == "csv" || ext == "tsv");
+    if is_csv {
+        import_csv(reader, filter, &mut sink)
+    } else {
+        import_jsonl(reader, filter, &mut sink)
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonProduct {
+    #[serde(default)]
+    code: Value,
+    #[serde(default)]
+    product_name: Value,
+    #[serde(default)]
+    brands: Value,
+    #[serde(default)]
+    countries_tags: Vec<String>,
+    #[serde(default)]
+    serving_size: Value,
+    #[serde(default)]
+    serving_quantity: Value,
+    #[serde(default)]
+    nutriments: HashMap<String, Value>,
+}
+
+/// Dump values are numbers or strings depending on the product.
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+    .filter(|n: &f64| n.is_finite() && *n >= 0.0)
+}
+
+/// Builds a product from its fields, with nutrients per 100 g.
+fn product(
+    code: Option<String>,
+    name: Option<String>,
+    brands: Option<String>,
+    serving_size: Option<String>,
+    serving_quantity: Option<f64>,
+    nutrient: impl Fn(&str) -> Option<f64>,
+) -> Option<Product> {
+    let calories =
+        nutrient("energy-kcal_100g").or_else(|| nutrient("energy_100g").map(|kj| kj / 4.184))?;
+    Some(Product {
+        barcode: code?,
+        name: name?,
+        // The first brand is the manufacturer; others are often retailers.
+        brand: brands.and_then(|b| b.split(',').next().map(|b| b.trim().to_string())),
+        protein: round(nutrient("proteins_100g").unwrap_or(0.0)),
+        fat: round(nutrient("fat_100g").unwrap_or(0.0)),
+        carbs: round(nutrient("carbohydrates_100g").unwrap_or(0.0)),
+        calories: round(calories),
+        serving_size,
+        serving_grams: serving_quantity.filter(|grams| *grams > 0.0),
+    })
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+fn import_jsonl(
+    reader: impl BufRead,
+    filter: &Filter,
+    sink: &mut impl FnMut(Product) -> Result<(), Box<dyn Error + Send + Sync>>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let json: JsonProduct = serde_json::from_str(&line)?;
+        if !filter.matches(&json.countries_tags) {
+            continue;
+        }
+        if let Some(product) = from_json(json) {
+            sink(product)?;
+        }
+    }
+    Ok(())
+}
+
+fn from_json(json: JsonProduct) -> Option<Product> {
+    product(
+        text(&json.code),
+        text(&json.product_name),
+        text(&json.brands),
+        text(&json.serving_size),
+        number(&json.serving_quantity),
+        |name| json.nutriments.get(name).and_then(number),
+    )
+}
+
+/// Converts a product object as returned by the Open Food Facts API, which
+/// has the same fields as the JSONL dump. Returns `None` if it lacks a name
+/// or energy value.
+pub fn parse_product(value: Value) -> Result<Option<Product>, serde_json::Error> {
+    Ok(from_json(serde_json::from_value(value)?))
+}
+
+fn import_csv(
+    reader: impl Read,
+    filter: &Filter,
+    sink: &mut impl FnMut(Product) -> Result<(), Box<dyn Error + Send + Sync>>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .quoting(false)
+        .flexible(true)
+        .from_reader(reader);
+    let headers: HashMap<String, usize> = reader
+        .headers()?
+        .iter()
+        .enumerate()
+        .map(|(i, h)| (h.to_string(), i))
+        .collect();
+    if !headers.contains_key("code") {
+        return Err("Not an Open Food Facts CSV export: no code column".into());
+    }
+
+    for record in reader.records() {
+        let record = record?;
+        let field = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|&i| record.get(i))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        let countries: Vec<String> = field("countries_tags")
+            .map(|tags| tags.split(',').map(str::to_string).collect())
+            .unwrap_or_default();
+        if !filter.matches(&countries) {
+            continue;
+        }
+        let found = product(
+            field("code").map(str::to_string),
+            field("product_name").map(str::to_string),
+            field("brands").map(str::to_string),
+            field("serving_size").map(str::to_string),
+            field("serving_quantity").and_then(|q| number(&Value::String(q.to_string()))),
+            |name| field(name).and_then(|v| number(&Value::String(v.to_string()))),
+        );
+        if let Some(product) = found {
+            sink(product)?;
+        }
+    }
+    Ok(())
+}
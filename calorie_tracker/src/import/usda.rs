@@ -0,0 +1,356 @@
+use crate::food::Food;
+use crate::library::LibraryFood;
+use serde::de::{DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+// FoodData Central nutrient IDs. All amounts are per 100 g.
+const ENERGY_KCAL: u32 = 1008;
+const ENERGY_KJ: u32 = 1062;
+const ENERGY_ATWATER_GENERAL: u32 = 2047;
+const ENERGY_ATWATER_SPECIFIC: u32 = 2048;
+const PROTEIN: u32 = 1003;
+const FAT: u32 = 1004;
+const FAT_NLEA: u32 = 1085;
+const CARBS: u32 = 1005;
+const CARBS_SUMMATION: u32 = 1050;
+
+/// Food data types worth logging; the CSV download also holds lab samples
+/// and acquisition records.
+const FOOD_DATA_TYPES: [&str; 4] = [
+    "foundation_food",
+    "sr_legacy_food",
+    "survey_fndds_food",
+    "branded_food",
+];
+
+/// Reads a FoodData Central download from https://fdc.nal.usda.gov/download-datasets.html:
+/// either an extracted JSON file (such as `FoodData_Central_foundation_food_json_*.json`)
+/// or the directory of an extracted CSV download containing `food.csv`.
+pub fn import(path: &str) -> Result<Vec<LibraryFood>, Box<dyn Error + Send + Sync>> {
+    let path = Path::new(path);
+    if path.is_dir() {
+        import_csv(path)
+    } else if path.extension().is_some_and(|ext| ext == "csv") {
+        import_csv(path.parent().unwrap_or(Path::new(".")))
+    } else {
+        import_json(path)
+    }
+}
+
+/// Nutrient amounts collected for one food.
+#[derive(Debug, Default)]
+struct Nutrients(HashMap<u32, f64>);
+
+impl Nutrients {
+    fn record(&mut self, id: u32, amount: f64) {
+        let relevant = matches!(
+            id,
+            ENERGY_KCAL
+                | ENERGY_KJ
+                | ENERGY_ATWATER_GENERAL
+                | ENERGY_ATWATER_SPECIFIC
+                | PROTEIN
+                | FAT
+                | FAT_NLEA
+                | CARBS
+                | CARBS_SUMMATION
+        );
+        if relevant && amount.is_finite() && amount >= 0.0 {
+            self.0.insert(id, amount);
+        }
+    }
+
+    fn first(&self, ids: &[u32]) -> Option<f64> {
+        ids.iter().find_map(|id| self.0.get(id).copied())
+    }
+
+    /// Maps the nutrients onto a 100 g `Food`. Energy falls back from kcal to
+    /// the Atwater values, kJ and finally the macronutrients.
+    fn into_food(self, name: &str) -> Option<Food> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let protein = self.first(&[PROTEIN]).unwrap_or(0.0);
+        let fat = self.first(&[FAT, FAT_NLEA]).unwrap_or(0.0);
+        let carbs = self.first(&[CARBS, CARBS_SUMMATION]).unwrap_or(0.0);
+        let calories = self
+            .first(&[ENERGY_KCAL, ENERGY_ATWATER_GENERAL, ENERGY_ATWATER_SPECIFIC])
+            .or_else(|| self.first(&[ENERGY_KJ]).map(|kj| kj / 4.184))
+            .unwrap_or(protein * 4.0 + fat * 9.0 + carbs * 4.0);
+        Some(Food::new(
+            name,
+            100.0,
+            "g",
+            round(protein),
+            round(fat),
+            round(carbs),
+            round(calories),
+        ))
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+/// Barcode and serving size of a branded food.
+#[derive(Debug, Default)]
+struct Branded {
+    barcode: Option<String>,
+    serving_size: Option<f64>,
+    serving_size_unit: Option<String>,
+    /// Such as "2 cookies".
+    household_serving: Option<String>,
+}
+
+impl Branded {
+    /// Grams per serving, when the serving size is given in grams.
+    fn serving_grams(&self) -> Option<f64> {
+        let unit = self.serving_size_unit.as_deref()?.trim().to_lowercase();
+        match unit.as_str() {
+            "g" | "grm" => self.serving_size.filter(|grams| *grams > 0.0),
+            _ => None,
+        }
+    }
+
+    /// The serving as printed on the package, such as "2 cookies (30 g)".
+    fn serving_description(&self) -> Option<String> {
+        let household = self
+            .household_serving
+            .as_deref()
+            .map(str::trim)
+            .filter(|text| !text.is_empty());
+        match (household, self.serving_grams()) {
+            (Some(household), Some(grams)) => Some(format!("{} ({} g)", household, grams)),
+            (Some(household), None) => Some(household.to_string()),
+            (None, Some(grams)) => Some(format!("{} g", grams)),
+            (None, None) => None,
+        }
+    }
+}
+
+fn library_food(
+    fdc_id: u64,
+    name: &str,
+    branded: Branded,
+    nutrients: Nutrients,
+) -> Option<LibraryFood> {
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let serving_size = branded.serving_description();
+    let serving_grams = branded.serving_grams();
+    nutrients.into_food(name).map(|food| LibraryFood {
+        id: format!("fdc:{}", fdc_id),
+        food,
+        barcode: branded.barcode.filter(|code| !code.trim().is_empty()),
+        serving_size,
+        serving_grams,
+    })
+}
+
+fn import_csv(dir: &Path) -> Result<Vec<LibraryFood>, Box<dyn Error + Send + Sync>> {
+    let mut names: HashMap<u64, String> = HashMap::new();
+    let mut reader = csv::Reader::from_path(dir.join("food.csv"))?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| format!("food.csv has no {} column", name))
+    };
+    let (id_column, type_column, name_column) = (
+        column("fdc_id")?,
+        column("data_type")?,
+        column("description")?,
+    );
+    for record in reader.records() {
+        let record = record?;
+        let data_type = record.get(type_column).unwrap_or_default();
+        if !FOOD_DATA_TYPES.contains(&data_type) {
+            continue;
+        }
+        if let Some(Ok(fdc_id)) = record.get(id_column).map(str::parse) {
+            names.insert(
+                fdc_id,
+                record.get(name_column).unwrap_or_default().to_string(),
+            );
+        }
+    }
+
+    let mut nutrients: HashMap<u64, Nutrients> = HashMap::new();
+    let mut reader = csv::Reader::from_path(dir.join("food_nutrient.csv"))?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| format!("food_nutrient.csv has no {} column", name))
+    };
+    let (id_column, nutrient_column, amount_column) =
+        (column("fdc_id")?, column("nutrient_id")?, column("amount")?);
+    for record in reader.records() {
+        let record = record?;
+        let parsed = (
+            record.get(id_column).map(str::parse::<u64>),
+            record.get(nutrient_column).map(str::parse::<u32>),
+            record.get(amount_column).map(str::parse::<f64>),
+        );
+        if let (Some(Ok(fdc_id)), Some(Ok(nutrient_id)), Some(Ok(amount))) = parsed {
+            if names.contains_key(&fdc_id) {
+                nutrients
+                    .entry(fdc_id)
+                    .or_default()
+                    .record(nutrient_id, amount);
+            }
+        }
+    }
+
+    // Branded foods list their barcode and serving size in a separate file.
+    let mut branded: HashMap<u64, Branded> = HashMap::new();
+    if dir.join("branded_food.csv").exists() {
+        let mut reader = csv::Reader::from_path(dir.join("branded_food.csv"))?;
+        let headers = reader.headers()?.clone();
+        let column = |name: &str| headers.iter().position(|h| h == name);
+        let id_column = column("fdc_id").ok_or("branded_food.csv has no fdc_id column")?;
+        let (barcode_column, size_column, unit_column, household_column) = (
+            column("gtin_upc"),
+            column("serving_size"),
+            column("serving_size_unit"),
+            column("household_serving_fulltext"),
+        );
+        for record in reader.records() {
+            let record = record?;
+            let field = |column: Option<usize>| {
+                column
+                    .and_then(|column| record.get(column))
+                    .filter(|value| !value.is_empty())
+            };
+            if let Some(Ok(fdc_id)) = record.get(id_column).map(str::parse::<u64>) {
+                branded.insert(
+                    fdc_id,
+                    Branded {
+                        barcode: field(barcode_column).map(str::to_string),
+                        serving_size: field(size_column).and_then(|size| size.parse().ok()),
+                        serving_size_unit: field(unit_column).map(str::to_string),
+                        household_serving: field(household_column).map(str::to_string),
+                    },
+                );
+            }
+        }
+    }
+
+    let mut foods: Vec<LibraryFood> = nutrients
+        .into_iter()
+        .filter_map(|(fdc_id, nutrients)| {
+            let branded = branded.remove(&fdc_id).unwrap_or_default();
+            library_food(fdc_id, &names[&fdc_id], branded, nutrients)
+        })
+        .collect();
+    foods.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(foods)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonFood {
+    fdc_id: u64,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    food_nutrients: Vec<JsonFoodNutrient>,
+    gtin_upc: Option<String>,
+    serving_size: Option<f64>,
+    serving_size_unit: Option<String>,
+    household_serving_full_text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonFoodNutrient {
+    nutrient: Option<JsonNutrient>,
+    amount: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct JsonNutrient {
+    id: u32,
+}
+
+/// Streams the JSON download, which is an object mapping a data type such as
+/// `FoundationFoods` to an array of foods. The branded foods file is several
+/// gigabytes, so foods are converted one at a time.
+fn import_json(path: &Path) -> Result<Vec<LibraryFood>, Box<dyn Error + Send + Sync>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let mut foods = Vec::new();
+    deserializer.deserialize_map(FoodLists(&mut |food: JsonFood| {
+        let mut nutrients = Nutrients::default();
+        for entry in food.food_nutrients {
+            if let (Some(nutrient), Some(amount)) = (entry.nutrient, entry.amount) {
+                nutrients.record(nutrient.id, amount);
+            }
+        }
+        let branded = Branded {
+            barcode: food.gtin_upc,
+            serving_size: food.serving_size,
+            serving_size_unit: food.serving_size_unit,
+            household_serving: food.household_serving_full_text,
+        };
+        foods.extend(library_food(
+            food.fdc_id,
+            &food.description,
+            branded,
+            nutrients,
+        ));
+    }))?;
+    Ok(foods)
+}
+
+struct FoodLists<'a, F>(&'a mut F);
+
+impl<'de, F: FnMut(JsonFood)> Visitor<'de> for FoodLists<'_, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an object of FoodData Central food lists")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while map.next_key::<String>()?.is_some() {
+            map.next_value_seed(FoodList(&mut *self.0))?;
+        }
+        Ok(())
+    }
+}
+
+struct FoodList<'a, F>(&'a mut F);
+
+impl<'de, F: FnMut(JsonFood)> DeserializeSeed<'de> for FoodList<'_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F: FnMut(JsonFood)> Visitor<'de> for FoodList<'_, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a list of foods")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(food) = seq.next_element::<JsonFood>()? {
+            (self.0)(food);
+        }
+        Ok(())
+    }
+}
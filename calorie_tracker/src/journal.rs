@@ -0,0 +1,349 @@
+use crate::app::Workout;
+use crate::crypto::Cipher;
+use crate::day::{index_days, Day, Days};
+use crate::food::Food;
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// A single change to the recorded days.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Change {
+    /// Days that existed before the journal was started.
+    Imported {
+        days: Vec<Day>,
+    },
+    /// Replaces all days with the output of a repair.
+    Repaired {
+        days: Vec<Day>,
+    },
+    FoodAdded {
+        date: NaiveDate,
+        food: Food,
+        quantity: f64,
+    },
+    FoodRemoved {
+        date: NaiveDate,
+        index: usize,
+    },
+    QuantityChanged {
+        date: NaiveDate,
+        index: usize,
+        quantity: f64,
+    },
+    /// Replaces a food entry.
+    FoodEdited {
+        date: NaiveDate,
+        index: usize,
+        food: Food,
+    },
+    /// Replaces the day's workouts; written before days held several.
+    WorkoutSet {
+        date: NaiveDate,
+        workout: Workout,
+    },
+    WorkoutAdded {
+        date: NaiveDate,
+        workout: Workout,
+    },
+    WorkoutRemoved {
+        date: NaiveDate,
+        index: usize,
+    },
+    /// Replaces a workout entry.
+    WorkoutEdited {
+        date: NaiveDate,
+        index: usize,
+        workout: Workout,
+    },
+    DayReset {
+        date: NaiveDate,
+    },
+    DayRegistered {
+        date: NaiveDate,
+    },
+    /// Replaces a whole day, or removes it when `day` is `None`. Used by undo and redo.
+    DayRestored {
+        date: NaiveDate,
+        day: Option<Day>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub seq: u64,
+    pub at: DateTime<Local>,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+impl Change {
+    /// The day this change applies to, or `None` for changes spanning all days.
+    pub fn date(&self) -> Option<NaiveDate> {
+        match self {
+            Change::Imported { .. } | Change::Repaired { .. } => None,
+            Change::FoodAdded { date, .. }
+            | Change::FoodRemoved { date, .. }
+            | Change::QuantityChanged { date, .. }
+            | Change::FoodEdited { date, .. }
+            | Change::WorkoutSet { date, .. }
+            | Change::WorkoutAdded { date, .. }
+            | Change::WorkoutRemoved { date, .. }
+            | Change::WorkoutEdited { date, .. }
+            | Change::DayReset { date }
+            | Change::DayRegistered { date }
+            | Change::DayRestored { date, .. } => Some(*date),
+        }
+    }
+
+    /// The position of the food or workout this change targets, if it
+    /// targets a single entry.
+    pub fn index_mut(&mut self) -> Option<&mut usize> {
+        match self {
+            Change::FoodRemoved { index, .. }
+            | Change::QuantityChanged { index, .. }
+            | Change::FoodEdited { index, .. }
+            | Change::WorkoutRemoved { index, .. }
+            | Change::WorkoutEdited { index, .. } => Some(index),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&self, days: &mut Days) {
+        let day = match self.date() {
+            Some(date) => days.entry(date).or_insert_with(|| Day::new(date)),
+            None => {
+                if let Change::Imported { days: replaced } | Change::Repaired { days: replaced } =
+                    self
+                {
+                    *days = index_days(replaced.clone());
+                }
+                return;
+            }
+        };
+        match self {
+            Change::FoodAdded { food, quantity, .. } => day.add_food(food.clone(), *quantity),
+            Change::FoodRemoved { index, .. } => day.remove_food(*index),
+            Change::QuantityChanged {
+                index, quantity, ..
+            } => {
+                if let Some(food) = day.foods.get_mut(*index) {
+                    food.quantity = *quantity;
+                }
+            }
+            Change::FoodEdited { index, food, .. } => {
+                if let Some(entry) = day.foods.get_mut(*index) {
+                    *entry = food.clone();
+                }
+            }
+            Change::WorkoutSet { workout, .. } => day.set_workout(workout.clone()),
+            Change::WorkoutAdded { workout, .. } => day.add_workout(workout.clone()),
+            Change::WorkoutRemoved { index, .. } => day.remove_workout(*index),
+            Change::WorkoutEdited { index, workout, .. } => {
+                if let Some(entry) = day.workouts.get_mut(*index) {
+                    *entry = workout.clone();
+                }
+            }
+            Change::DayReset { .. } => day.reset(),
+            Change::DayRegistered { .. } => {}
+            Change::DayRestored {
+                date,
+                day: restored,
+            } => match restored {
+                Some(restored) => *day = restored.clone(),
+                None => {
+                    days.remove(date);
+                }
+            },
+            Change::Imported { .. } | Change::Repaired { .. } => unreachable!(),
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Imported { days } => write!(f, "Imported {} days", days.len()),
+            Change::Repaired { days } => write!(f, "Repaired data ({} days)", days.len()),
+            Change::FoodAdded { food, quantity, .. } => {
+                write!(f, "Added {} x {}", quantity, food.name)
+            }
+            Change::FoodRemoved { index, .. } => write!(f, "Removed entry #{}", index + 1),
+            Change::QuantityChanged {
+                index, quantity, ..
+            } => write!(f, "Changed entry #{} quantity to {}", index + 1, quantity),
+            Change::FoodEdited { index, food, .. } => {
+                write!(f, "Edited entry #{} ({})", index + 1, food.name)
+            }
+            Change::WorkoutSet { workout, .. } => {
+                write!(
+                    f,
+                    "Set {:?} workout ({} min)",
+                    workout.workout_type, workout.duration
+                )
+            }
+            Change::WorkoutAdded { workout, .. } => {
+                write!(f, "Added {} ({} min)", workout.label(), workout.duration)
+            }
+            Change::WorkoutRemoved { index, .. } => {
+                write!(f, "Removed workout #{}", index + 1)
+            }
+            Change::WorkoutEdited { index, workout, .. } => {
+                write!(f, "Edited workout #{} ({})", index + 1, workout.label())
+            }
+            Change::DayReset { .. } => write!(f, "Reset day"),
+            Change::DayRegistered { .. } => write!(f, "Registered day"),
+            Change::DayRestored { day: Some(_), .. } => write!(f, "Restored day"),
+            Change::DayRestored { day: None, .. } => write!(f, "Removed day"),
+        }
+    }
+}
+
+/// Append-only log of events, one JSON object per line. With a cipher, each
+/// line is encrypted on its own so appends stay cheap.
+///
+/// `offset` and `seq` track how far this process has read, so that events
+/// appended by other processes can be picked up with `read_new`.
+pub struct Journal {
+    path: String,
+    offset: u64,
+    seq: u64,
+    cipher: Option<Cipher>,
+}
+
+impl Journal {
+    pub fn open(path: &str, offset: u64, seq: u64, cipher: Option<Cipher>) -> Self {
+        Self {
+            path: path.to_string(),
+            offset,
+            seq,
+            cipher,
+        }
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Reads events written since the last read or append.
+    pub fn read_new(&mut self) -> Result<Vec<Event>, Box<dyn Error + Send + Sync>> {
+        let path = Path::new(&self.path);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut file = File::open(path)?;
+        if file.metadata()?.len() < self.offset {
+            return Err(format!("Journal {} was truncated by another process", self.path).into());
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+
+        let mut reader = BufReader::new(file);
+        let mut events = Vec::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            // A line without a newline is an interrupted write; leave it for later.
+            if read == 0 || !line.ends_with('\n') {
+                break;
+            }
+            let event: Event = if line.starts_with('{') {
+                serde_json::from_str(&line)?
+            } else {
+                let cipher = self.cipher.as_ref().ok_or_else(|| {
+                    format!("{} is encrypted; a passphrase is required", self.path)
+                })?;
+                serde_json::from_str(&cipher.decrypt_line(&line)?)?
+            };
+            self.offset += read as u64;
+            self.seq = self.seq.max(event.seq);
+            events.push(event);
+        }
+        Ok(events)
+    }
+
+    pub fn append(&mut self, change: Change) -> Result<Event, Box<dyn Error + Send + Sync>> {
+        let event = Event {
+            seq: self.seq + 1,
+            at: Local::now(),
+            change,
+        };
+        let mut line = encode_line(&event, self.cipher.as_ref())?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+
+        self.offset += line.len() as u64;
+        self.seq = event.seq;
+        Ok(event)
+    }
+}
+
+fn encode_line(
+    event: &Event,
+    cipher: Option<&Cipher>,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let line = serde_json::to_string(event)?;
+    match cipher {
+        Some(cipher) => cipher.encrypt_line(&line),
+        None => Ok(line),
+    }
+}
+
+/// Reads every event in the journal at `path`.
+pub fn read_all(
+    path: &str,
+    cipher: Option<&Cipher>,
+) -> Result<Vec<Event>, Box<dyn Error + Send + Sync>> {
+    Journal::open(path, 0, 0, cipher.cloned()).read_new()
+}
+
+/// Replaces the journal at `path` with `events` encoded for `cipher`, and
+/// returns its new length.
+pub fn rewrite(
+    path: &str,
+    events: &[Event],
+    cipher: Option<&Cipher>,
+) -> Result<u64, Box<dyn Error + Send + Sync>> {
+    let mut contents = String::new();
+    for event in events {
+        contents.push_str(&encode_line(event, cipher)?);
+        contents.push('\n');
+    }
+    let tmp_path = format!("{}.tmp", path);
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(contents.len() as u64)
+}
+
+/// Rebuilds the days as they were at `at` by replaying the journal from the start.
+pub fn replay_until(
+    path: &str,
+    at: DateTime<Local>,
+    cipher: Option<&Cipher>,
+) -> Result<Days, Box<dyn Error + Send + Sync>> {
+    let mut days = Days::new();
+    for event in read_all(path, cipher)? {
+        if event.at > at {
+            break;
+        }
+        event.change.apply(&mut days);
+    }
+    Ok(days)
+}
@@ -1,8 +1,17 @@
 pub mod api;
 pub mod app;
+pub mod config;
+pub mod crypto;
 pub mod day;
 pub mod food;
+pub mod history;
+pub mod import;
+pub mod journal;
+pub mod library;
+pub mod parser;
+pub mod products;
 pub mod storage;
+pub mod validate;
 
-pub use app::{App, AppResult, Gender, Workout, WorkoutType};
-pub use food::Food;
+pub use app::{App, AppResult, Gender, Profile, Workout, WorkoutType};
+pub use food::{Food, Meal};
@@ -0,0 +1,157 @@
+use crate::crypto::Cipher;
+use crate::food::Food;
+use crate::products::{barcode_key, Product};
+use crate::storage::{read_data, write_data};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Lowest match score per character of the query. Whole words in the name
+/// score above it and small typos just reach it, while letters scattered
+/// through the name or inside another word, such as "tea" in "Beef, steak",
+/// fall below.
+const MIN_SCORE_PER_CHAR: i64 = 20;
+
+/// A food from an imported database, with nutrient values per 100 g.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LibraryFood {
+    /// Identifies the food in its source, such as `fdc:171287`, so importing
+    /// the same database again replaces it.
+    pub id: String,
+    pub food: Food,
+    /// UPC or EAN code of branded foods.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub barcode: Option<String>,
+    /// Serving size of branded foods as printed on the package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serving_size: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serving_grams: Option<f64>,
+}
+
+impl LibraryFood {
+    /// The food as a product with the given barcode, for the barcode flow.
+    pub fn to_product(&self, barcode: &str) -> Product {
+        Product {
+            barcode: barcode.to_string(),
+            name: self.food.name.clone(),
+            brand: None,
+            protein: self.food.protein,
+            fat: self.food.fat,
+            carbs: self.food.carbs,
+            calories: self.food.calories,
+            serving_size: self.serving_size.clone(),
+            serving_grams: self.serving_grams,
+        }
+    }
+
+    /// The food with its values scaled to `grams`.
+    pub fn portion(&self, grams: f64) -> Food {
+        let factor = grams / 100.0;
+        Food::new(
+            &self.food.name,
+            grams,
+            "g",
+            self.food.protein * factor,
+            self.food.fat * factor,
+            self.food.carbs * factor,
+            self.food.calories * factor,
+        )
+    }
+}
+
+/// Foods available for logging without network access.
+#[derive(Debug, Default)]
+pub struct Library {
+    foods: Vec<LibraryFood>,
+    index: HashMap<String, usize>, // position of each ID in `foods`
+}
+
+impl Library {
+    pub fn load(path: &str, cipher: Option<&Cipher>) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let foods: Vec<LibraryFood> = match read_data(path, cipher)? {
+            Some(contents) => serde_json::from_slice(&contents)?,
+            None => Vec::new(),
+        };
+        let mut library = Library::default();
+        library.extend(foods);
+        Ok(library)
+    }
+
+    pub fn save(
+        &self,
+        path: &str,
+        cipher: Option<&Cipher>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let serialized = serde_json::to_vec(&self.foods)?;
+        write_data(path, &serialized, cipher)
+    }
+
+    /// Adds foods, replacing those with the same ID. Returns how many were added
+    /// or replaced.
+    pub fn extend(&mut self, foods: impl IntoIterator<Item = LibraryFood>) -> usize {
+        let mut count = 0;
+        for food in foods {
+            match self.index.get(&food.id) {
+                Some(&position) => self.foods[position] = food,
+                None => {
+                    self.index.insert(food.id.clone(), self.foods.len());
+                    self.foods.push(food);
+                }
+            }
+            count += 1;
+        }
+        count
+    }
+
+    pub fn get(&self, id: &str) -> Option<&LibraryFood> {
+        self.index.get(id).map(|&position| &self.foods[position])
+    }
+
+    pub fn foods(&self) -> &[LibraryFood] {
+        &self.foods
+    }
+
+    pub fn len(&self) -> usize {
+        self.foods.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.foods.is_empty()
+    }
+
+    /// The food with the given UPC or EAN code, ignoring leading zeros.
+    pub fn find_barcode(&self, barcode: &str) -> Option<&LibraryFood> {
+        let key = barcode_key(barcode)?;
+        self.foods.iter().find(|food| {
+            food.barcode
+                .as_deref()
+                .and_then(barcode_key)
+                .is_some_and(|k| k == key)
+        })
+    }
+
+    /// Foods whose name matches `query` closely enough, best match first.
+    pub fn search(&self, matcher: &SkimMatcherV2, query: &str) -> Vec<(&LibraryFood, i64)> {
+        let min_score = MIN_SCORE_PER_CHAR * query.chars().count() as i64;
+        let mut results: Vec<(&LibraryFood, i64)> = self
+            .foods
+            .iter()
+            .filter_map(|food| {
+                matcher
+                    .fuzzy_match(&food.food.name, query)
+                    .filter(|&score| score >= min_score)
+                    .map(|score| (food, score))
+            })
+            .collect();
+        // Prefer shorter names on equal scores, e.g. "Egg, whole, raw" over
+        // the dozens of branded products that mention egg.
+        results.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then_with(|| a.0.food.name.len().cmp(&b.0.food.name.len()))
+        });
+        results
+    }
+}
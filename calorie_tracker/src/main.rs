@@ -1,13 +1,87 @@
+mod calendar;
+mod cli;
+mod tui;
+
+use calorie_tracker::api::{ApiError, NutritionInfo};
 use calorie_tracker::app::{Workout, WorkoutType};
-use calorie_tracker::{App, AppResult, Food, Gender};
-use chrono::NaiveDate;
+use calorie_tracker::config::{Config, Provider};
+use calorie_tracker::food::parse_time;
+use calorie_tracker::import::open_food_facts::Filter;
+use calorie_tracker::library::LibraryFood;
+use calorie_tracker::products::{is_valid_barcode, Product};
+use calorie_tracker::storage::{Layout, Store};
+use calorie_tracker::{App, AppResult, Food, Gender, Meal};
+use chrono::{Datelike, NaiveDate};
+use clap::Parser;
+use cli::Cli;
 use colored::*;
 use core::cmp::Ordering;
-use dialoguer::{theme::ColorfulTheme, Input, Select};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, queue};
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, MultiSelect, Password, Select, Sort};
 use std::cmp::min;
+use std::error::Error;
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+/// Config file used unless `CALORIE_TRACKER_CONFIG` names another file.
+const CONFIG_FILE: &str = "config.json";
+
+fn config_path() -> String {
+    std::env::var("CALORIE_TRACKER_CONFIG").unwrap_or_else(|_| CONFIG_FILE.to_string())
+}
+
+/// Data file used unless `CALORIE_TRACKER_DATA` names another file, or a
+/// directory for the one-file-per-day layout.
+const DATA_FILE: &str = "calories.json";
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            cli::exit_code(e.as_ref())
+        }
+    }
+}
 
-fn main() -> AppResult<()> {
-    let mut app = App::new("calories.json")?;
+fn run(cli: Cli) -> AppResult<()> {
+    let data_path = std::env::var("CALORIE_TRACKER_DATA").unwrap_or_else(|_| DATA_FILE.to_string());
+    let store = Store::new(&data_path);
+    let passphrase = if store.is_encrypted()? {
+        Some(read_passphrase()?)
+    } else {
+        None
+    };
+    let mut app = App::open_store(store, passphrase.as_deref())?;
+    app.set_config(Config::load(&config_path())?)?;
+
+    if let Some(command) = cli.command {
+        return cli::run(&mut app, command);
+    }
+
+    let report = app.check();
+    if !report.is_ok() {
+        println!(
+            "{}",
+            format!(
+                "⚠️ Found {} problems in the data file:",
+                report.problems.len()
+            )
+            .yellow()
+        );
+        for problem in &report.problems {
+            println!("  • {}", problem);
+        }
+        println!(
+            "{}",
+            "Use Data Management → Check and repair data to fix them.".yellow()
+        );
+        pause()?;
+    }
 
     loop {
         print!("\x1B[2J\x1B[1;1H"); // Clear screen
@@ -26,12 +100,22 @@ fn main() -> AppResult<()> {
         println!();
 
         let choices = vec![
-            "📅 Day Management",
-            "🍽️ Food Tracking",
-            "💪 Workout Management",
-            "📊 Statistics and Reports",
-            "👤 User Settings",
-            "❌ Exit",
+            "📅 Day Management".to_string(),
+            "🍽️ Food Tracking".to_string(),
+            "💪 Workout Management".to_string(),
+            "📊 Statistics and Reports".to_string(),
+            "🖥️ Dashboard".to_string(),
+            "👤 User Settings".to_string(),
+            "🗄️ Data Management".to_string(),
+            match app.next_undo() {
+                Some(description) => format!("↩️ Undo: {}", description),
+                None => "↩️ Undo".to_string(),
+            },
+            match app.next_redo() {
+                Some(description) => format!("↪️ Redo: {}", description),
+                None => "↪️ Redo".to_string(),
+            },
+            "❌ Exit".to_string(),
         ];
 
         let selection = Select::with_theme(&ColorfulTheme::default())
@@ -45,8 +129,12 @@ fn main() -> AppResult<()> {
             1 => food_tracking_menu(&mut app)?,
             2 => workout_management_menu(&mut app)?,
             3 => statistics_menu(&app)?,
-            4 => user_settings_menu(&mut app)?,
-            5 => break,
+            4 => tui::run(&mut app)?,
+            5 => user_settings_menu(&mut app)?,
+            6 => data_management_menu(&mut app)?,
+            7 => undo(&mut app)?,
+            8 => redo(&mut app)?,
+            9 => break,
             _ => unreachable!(),
         }
     }
@@ -57,25 +145,34 @@ fn main() -> AppResult<()> {
 fn day_management_menu(app: &mut App) -> AppResult<()> {
     loop {
         let choices = vec![
-            "📅 Change day",
+            "⬅️ Previous day",
+            "➡️ Next day",
+            "📍 Today",
+            "📅 Jump to date",
+            "🗓️ Pick from calendar",
             "🔄 Reset day",
-            "📆 Register new day",
             "📊 Show current day",
+            "📜 Show change log",
             "⬅️ Back to main menu",
         ];
 
+        let prompt = format!("Day Management ({})", app.get_current_day()?.date);
         let selection = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("Day Management")
+            .with_prompt(prompt)
             .default(0)
             .items(&choices)
             .interact()?;
 
         match selection {
-            0 => change_day(app)?,
-            1 => reset_day(app)?,
-            2 => register_day(app)?,
-            3 => show_current_day(app)?,
-            4 => break,
+            0 => app.previous_day()?,
+            1 => app.next_day()?,
+            2 => app.go_to_today()?,
+            3 => change_day(app)?,
+            4 => pick_day(app)?,
+            5 => reset_day(app)?,
+            6 => show_current_day(app)?,
+            7 => show_change_log(app)?,
+            8 => break,
             _ => unreachable!(),
         }
     }
@@ -84,7 +181,13 @@ fn day_management_menu(app: &mut App) -> AppResult<()> {
 
 fn workout_management_menu(app: &mut App) -> AppResult<()> {
     loop {
-        let choices = vec!["💪 Add workout", "📊 View workouts", "⬅️ Back to main menu"];
+        let choices = vec![
+            "💪 Add workout",
+            "🗣️ Describe workout (e.g. 'ran 5k in 30 min')",
+            "📊 View workouts",
+            "🗑️ Remove workout",
+            "⬅️ Back to main menu",
+        ];
 
         let selection = Select::with_theme(&ColorfulTheme::default())
             .with_prompt("Workout Management")
@@ -94,8 +197,10 @@ fn workout_management_menu(app: &mut App) -> AppResult<()> {
 
         match selection {
             0 => add_workout(app)?,
-            1 => view_workouts(app)?,
-            2 => break,
+            1 => describe_workout(app)?,
+            2 => view_workouts(app)?,
+            3 => remove_workout(app)?,
+            4 => break,
             _ => unreachable!(),
         }
     }
@@ -130,7 +235,11 @@ fn statistics_menu(app: &App) -> AppResult<()> {
 
 fn user_settings_menu(app: &mut App) -> AppResult<()> {
     loop {
-        let choices = vec!["👤 Set user info", "⬅️ Back to main menu"];
+        let choices = vec![
+            "👤 Set user info",
+            "🌐 Nutrition providers",
+            "⬅️ Back to main menu",
+        ];
 
         let selection = Select::with_theme(&ColorfulTheme::default())
             .with_prompt("User Settings")
@@ -140,98 +249,986 @@ fn user_settings_menu(app: &mut App) -> AppResult<()> {
 
         match selection {
             0 => set_user_info(app)?,
-            1 => break,
+            1 => choose_providers(app)?,
+            2 => break,
             _ => unreachable!(),
         }
     }
-    Ok(())
+    Ok(())
+}
+
+fn data_management_menu(app: &mut App) -> AppResult<()> {
+    loop {
+        let choices = vec![
+            "🩺 Check and repair data",
+            "🔒 Encrypt or change passphrase",
+            "🔓 Decrypt data file",
+            "📂 Convert storage layout",
+            "🗜️ Archive or unarchive history",
+            "📥 Import USDA food database",
+            "📥 Import Open Food Facts products",
+            "🗄️ Nutrition search cache",
+            "⬅️ Back to main menu",
+        ];
+
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("Data Management")
+            .default(0)
+            .items(&choices)
+            .interact()?;
+
+        match selection {
+            0 => check_and_repair_data(app)?,
+            1 => encrypt_data(app)?,
+            2 => decrypt_data(app)?,
+            3 => convert_storage_layout(app)?,
+            4 => archive_history(app)?,
+            5 => import_usda(app)?,
+            6 => import_open_food_facts(app)?,
+            7 => manage_cache(app)?,
+            8 => break,
+            _ => unreachable!(),
+        }
+    }
+    Ok(())
+}
+
+fn check_and_repair_data(app: &mut App) -> AppResult<()> {
+    let report = app.check();
+    if report.is_ok() {
+        println!("\n{}", "✅ No problems found.".green());
+    } else {
+        println!(
+            "\n{}",
+            format!("⚠️ Found {} problems:", report.problems.len()).yellow()
+        );
+        for problem in &report.problems {
+            println!("  • {}", problem);
+        }
+
+        let repair = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Merge duplicate days, drop invalid entries and sort by date?")
+            .default(true)
+            .interact()?;
+        if repair {
+            app.repair()?;
+            println!("\n{}", "✅ Data repaired successfully!".green());
+        }
+    }
+    pause()?;
+    Ok(())
+}
+
+fn encrypt_data(app: &mut App) -> AppResult<()> {
+    let passphrase = new_passphrase()?;
+    app.set_passphrase(Some(&passphrase))?;
+    println!("\n{}", "✅ Data file encrypted successfully!".green());
+    pause()?;
+    Ok(())
+}
+
+fn decrypt_data(app: &mut App) -> AppResult<()> {
+    if !app.is_encrypted() {
+        println!("\n{}", "The data file is not encrypted.".yellow());
+    } else {
+        let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Store the data file unencrypted?")
+            .default(false)
+            .interact()?;
+        if confirmed {
+            app.set_passphrase(None)?;
+            println!("\n{}", "✅ Data file decrypted successfully!".green());
+        }
+    }
+    pause()?;
+    Ok(())
+}
+
+fn convert_storage_layout(app: &mut App) -> AppResult<()> {
+    let (current, suggested) = match app.layout() {
+        Layout::SingleFile => ("a single file", "calories"),
+        Layout::PerDay => ("one file per day", "calories.json"),
+    };
+    println!("\n{}", format!("Data is stored as {}.", current).cyan());
+
+    let path: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("New location (a .json file, or a directory for one file per day)")
+        .default(suggested.to_string())
+        .interact_text()?;
+
+    app.convert(&path, layout_for_path(&path))?;
+    println!("\n{}", format!("✅ Data copied to {}", path).green());
+    println!(
+        "{}",
+        format!("Set CALORIE_TRACKER_DATA={} to open it next time.", path).yellow()
+    );
+    pause()?;
+    Ok(())
+}
+
+fn archive_history(app: &mut App) -> AppResult<()> {
+    let years: Vec<String> = app.archived_years().iter().map(i32::to_string).collect();
+    if years.is_empty() {
+        println!("\n{}", "No days are archived.".cyan());
+    } else {
+        println!(
+            "\n{}",
+            format!("Archived years: {}", years.join(", ")).cyan()
+        );
+    }
+
+    let choices = vec!["🗜️ Archive days", "📤 Unarchive days", "⬅️ Back"];
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Archive")
+        .default(0)
+        .items(&choices)
+        .interact()?;
+    if selection == 2 {
+        return Ok(());
+    }
+
+    // By default, archive everything before the start of last year.
+    let today = chrono::Local::now().date_naive();
+    let last_year_start = NaiveDate::from_ymd_opt(today.year() - 1, 1, 1).unwrap();
+    let start: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("From (YYYY-MM-DD)")
+        .default("1970-01-01".to_string())
+        .interact_text()?;
+    let end: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("To (YYYY-MM-DD)")
+        .default(last_year_start.pred_opt().unwrap().to_string())
+        .interact_text()?;
+    let (start, end) = match (parse_date(&start), parse_date(&end)) {
+        (Ok(start), Ok(end)) => (start, end),
+        _ => {
+            println!(
+                "\n{}",
+                "❌ Invalid date format. Please use YYYY-MM-DD.".red()
+            );
+            pause()?;
+            return Ok(());
+        }
+    };
+
+    if selection == 0 {
+        let count = app.archive(start, end)?;
+        println!("\n{}", format!("✅ Archived {} days.", count).green());
+    } else {
+        let count = app.unarchive(start, end)?;
+        println!("\n{}", format!("✅ Restored {} days.", count).green());
+    }
+    pause()?;
+    Ok(())
+}
+
+fn import_usda(app: &mut App) -> AppResult<()> {
+    println!(
+        "\n{}",
+        "Download a dataset from https://fdc.nal.usda.gov/download-datasets.html and extract it."
+            .cyan()
+    );
+    let path: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("JSON file or CSV directory")
+        .interact_text()?;
+
+    println!(
+        "{}",
+        "Importing, this can take a while for large datasets...".cyan()
+    );
+    match app.import_usda(path.trim()) {
+        Ok(count) => println!("\n{}", format!("✅ Imported {} foods.", count).green()),
+        Err(e) => println!("\n{}", format!("❌ Import failed: {}", e).red()),
+    }
+    pause()?;
+    Ok(())
+}
+
+fn import_open_food_facts(app: &mut App) -> AppResult<()> {
+    println!(
+        "\n{}",
+        "Download a product dump (JSONL or CSV) from https://world.openfoodfacts.org/data.".cyan()
+    );
+    let path: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Dump file")
+        .interact_text()?;
+    let countries: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Only products sold in (comma-separated countries, empty for all)")
+        .allow_empty(true)
+        .interact_text()?;
+    let filter = Filter {
+        countries: countries
+            .split(',')
+            .map(str::trim)
+            .filter(|country| !country.is_empty())
+            .map(str::to_string)
+            .collect(),
+    };
+
+    println!(
+        "{}",
+        "Importing, this can take a while for large dumps...".cyan()
+    );
+    match app.import_open_food_facts(path.trim(), &filter) {
+        Ok(count) => println!("\n{}", format!("✅ Imported {} products.", count).green()),
+        Err(e) => println!("\n{}", format!("❌ Import failed: {}", e).red()),
+    }
+    pause()?;
+    Ok(())
+}
+
+fn print_cache_stats(app: &App) {
+    let stats = app.cache_stats();
+    println!(
+        "Cached queries: {}, foods: {}, expired: {}",
+        stats.queries, stats.foods, stats.expired
+    );
+    println!(
+        "Hits: {}, misses: {} this session; {} KB on disk",
+        stats.hits,
+        stats.misses,
+        stats.size / 1024
+    );
+}
+
+fn manage_cache(app: &mut App) -> AppResult<()> {
+    println!();
+    print_cache_stats(app);
+
+    let choices = vec!["🧹 Remove expired entries", "🗑️ Clear the cache", "⬅️ Back"];
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Nutrition search cache")
+        .default(0)
+        .items(&choices)
+        .interact()?;
+    if selection == 2 {
+        return Ok(());
+    }
+
+    let count = app.purge_cache(selection == 1)?;
+    println!("\n{}", format!("✅ Removed {} entries.", count).green());
+    pause()?;
+    Ok(())
+}
+
+fn parse_date(date: &str) -> AppResult<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date {}; use YYYY-MM-DD", date).into())
+}
+
+/// Asks for a date until a valid one is typed.
+fn input_date(prompt: &str, default: Option<NaiveDate>) -> AppResult<NaiveDate> {
+    let theme = ColorfulTheme::default();
+    let mut input = Input::<String>::with_theme(&theme);
+    input.with_prompt(prompt);
+    if let Some(date) = default {
+        input.default(date.to_string());
+    }
+    let date = input
+        .validate_with(|input: &String| parse_date(input.trim()).map(|_| ()))
+        .interact_text()?;
+    parse_date(date.trim())
+}
+
+fn layout_for_path(path: &str) -> Layout {
+    if path.ends_with(".json") {
+        Layout::SingleFile
+    } else {
+        Layout::PerDay
+    }
+}
+
+/// Passphrase for an encrypted data file, from `CALORIE_TRACKER_PASSPHRASE`
+/// or prompted for.
+fn read_passphrase() -> AppResult<String> {
+    if let Ok(passphrase) = std::env::var("CALORIE_TRACKER_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    Ok(Password::with_theme(&ColorfulTheme::default())
+        .with_prompt("Enter passphrase")
+        .interact()?)
+}
+
+fn new_passphrase() -> AppResult<String> {
+    Ok(Password::with_theme(&ColorfulTheme::default())
+        .with_prompt("Enter new passphrase")
+        .with_confirmation("Confirm passphrase", "Passphrases do not match")
+        .interact()?)
+}
+
+/// Non-interactive `doctor [--fix]` command. Fails when problems remain.
+fn doctor_command(app: &mut App, fix: bool) -> AppResult<()> {
+    let report = if fix { app.repair()? } else { app.check() };
+    for problem in &report.problems {
+        println!("{}", problem);
+    }
+    if report.is_ok() {
+        println!("No problems found.");
+        Ok(())
+    } else if fix {
+        println!("Fixed {} problems.", report.problems.len());
+        Ok(())
+    } else {
+        Err(format!(
+            "Found {} problems; run `doctor --fix` to repair them",
+            report.problems.len()
+        )
+        .into())
+    }
+}
+
+fn add_food(app: &mut App) -> AppResult<()> {
+    let choices = vec![
+        "✏️ Add food manually",
+        "🌐 Search and add food from API",
+        "📚 Add food from library",
+        "🏷️ Add packaged product",
+        "📷 Scan or enter barcode",
+        "⬅️ Back to main menu",
+    ];
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Choose an option")
+        .default(0)
+        .items(&choices)
+        .interact()?;
+
+    match selection {
+        0 => add_food_manually(app),
+        1 => search_and_add_food(app),
+        2 => add_food_from_library(app),
+        3 => add_product(app),
+        4 => add_by_barcode(app),
+        5 => Ok(()),
+        _ => unreachable!(),
+    }
+}
+
+fn add_food_manually(app: &mut App) -> AppResult<()> {
+    let name: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Enter food name")
+        .interact_text()?;
+
+    let quantity: f64 = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Enter quantity (e.g., 1, 2, 0.5)")
+        .interact_text()?;
+
+    let protein: f64 = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Enter protein content (grams)")
+        .interact_text()?;
+
+    let fat: f64 = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Enter fat content (grams)")
+        .interact_text()?;
+
+    let carbs: f64 = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Enter carbohydrate content (grams)")
+        .interact_text()?;
+
+    // Calculate calories
+    let calories = (protein * 4.0) + (fat * 9.0) + (carbs * 4.0);
+
+    let food = Food::new(&name, quantity, "serving", protein, fat, carbs, calories);
+    app.add_food_manually(food)?;
+
+    println!("\n{}", "✅ Food added successfully!".green());
+    pause()?;
+    Ok(())
+}
+
+fn add_food_from_library(app: &mut App) -> AppResult<()> {
+    if app.library()?.is_empty() {
+        println!("\n{}", "📚 The food library is empty.".yellow());
+        println!(
+            "{}",
+            "Import a food database under Data Management first.".yellow()
+        );
+        pause()?;
+        return Ok(());
+    }
+
+    let query: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Search the food library")
+        .interact_text()?;
+    let results = app.search_library(&query, 20)?;
+    if results.is_empty() {
+        println!("\n{}", "❌ No foods found matching the query.".yellow());
+        pause()?;
+        return Ok(());
+    }
+
+    let mut choices: Vec<String> = results
+        .iter()
+        .map(|(entry, _)| {
+            format!(
+                "{} (per 100 g: {:.0} calories, {:.1}g protein, {:.1}g fat, {:.1}g carbs)",
+                entry.food.name.green(),
+                entry.food.calories,
+                entry.food.protein,
+                entry.food.fat,
+                entry.food.carbs
+            )
+        })
+        .collect();
+    choices.push("⬅️ Back".to_string());
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select a food")
+        .default(0)
+        .items(&choices)
+        .interact()?;
+    if selection == results.len() {
+        return Ok(());
+    }
+
+    let grams = read_grams()?;
+    let food = results[selection].0.portion(grams);
+    app.add_food(food, grams)?;
+
+    println!("\n{}", "✅ Food added successfully!".green());
+    pause()?;
+    Ok(())
+}
+
+fn add_product(app: &mut App) -> AppResult<()> {
+    if app.products().is_empty()? {
+        println!("\n{}", "🏷️ The product database is empty.".yellow());
+        println!(
+            "{}",
+            "Import Open Food Facts products under Data Management first.".yellow()
+        );
+        pause()?;
+        return Ok(());
+    }
+
+    let query: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Product name or barcode")
+        .interact_text()?;
+    let query = query.trim();
+
+    let product = if query.chars().all(|c| c.is_ascii_digit()) {
+        app.find_product(query)?
+    } else {
+        let results = app.search_products(query, 20)?;
+        if results.is_empty() {
+            None
+        } else {
+            let mut choices: Vec<String> = results
+                .iter()
+                .map(|(product, _)| {
+                    format!(
+                        "{} (per 100 g: {:.0} calories, {:.1}g protein)",
+                        product.display_name().green(),
+                        product.calories,
+                        product.protein
+                    )
+                })
+                .collect();
+            choices.push("⬅️ Back".to_string());
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Select a product")
+                .default(0)
+                .items(&choices)
+                .interact()?;
+            if selection == results.len() {
+                return Ok(());
+            }
+            Some(results[selection].0.clone())
+        }
+    };
+
+    match product {
+        Some(product) => log_product(app, &product)?,
+        None => println!("\n{}", "❌ No product found.".yellow()),
+    }
+    pause()?;
+    Ok(())
+}
+
+fn add_by_barcode(app: &mut App) -> AppResult<()> {
+    let input: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Scan or type the barcode")
+        .interact_text()?;
+    let barcode: String = input
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect();
+    if !is_valid_barcode(&barcode) {
+        println!(
+            "\n{}",
+            format!("❌ {} is not a valid EAN or UPC code.", barcode).red()
+        );
+        pause()?;
+        return Ok(());
+    }
+
+    match app.lookup_barcode(&barcode) {
+        Ok(Some((product, source))) => {
+            println!("\n{}", format!("🔍 Found in {}.", source).cyan());
+            log_product(app, &product)?;
+        }
+        Ok(None) => {
+            println!("\n{}", "❌ No product found for this barcode.".yellow());
+            return add_food_manually(app);
+        }
+        Err(e) if is_not_configured(e.as_ref()) => {
+            println!(
+                "\n{}",
+                "❌ No product found for this barcode locally.".yellow()
+            );
+            println!("{}", format!("⚠️ {}", e).yellow());
+            return add_food_manually(app);
+        }
+        Err(e) => {
+            println!("\n{}", format!("❌ Barcode lookup failed: {}", e).red());
+            print_api_error_hint(e.as_ref());
+            return add_food_manually(app);
+        }
+    }
+    pause()?;
+    Ok(())
+}
+
+/// Shows `product` per 100 g and per serving, asks for a portion and logs it
+/// once confirmed.
+fn log_product(app: &mut App, product: &Product) -> AppResult<()> {
+    println!("\n{}", product.display_name().green().bold());
+    println!(
+        "  Per 100 g: {:.0} calories, {:.1}g protein, {:.1}g fat, {:.1}g carbs",
+        product.calories, product.protein, product.fat, product.carbs
+    );
+    if let Some(serving) = product.per_serving() {
+        println!(
+            "  Per serving ({}): {:.0} calories, {:.1}g protein, {:.1}g fat, {:.1}g carbs",
+            product
+                .serving_size
+                .clone()
+                .unwrap_or_else(|| format!("{} g", product.serving_grams.unwrap_or_default())),
+            serving.calories,
+            serving.protein,
+            serving.fat,
+            serving.carbs
+        );
+    }
+
+    let (food, quantity) = match product.serving_grams {
+        Some(serving_grams) => {
+            let description = product
+                .serving_size
+                .clone()
+                .unwrap_or_else(|| format!("{} g", serving_grams));
+            let choices = vec![
+                format!("Servings of {}", description),
+                "Amount in grams".to_string(),
+            ];
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Log by")
+                .default(0)
+                .items(&choices)
+                .interact()?;
+            if selection == 0 {
+                let servings: f64 = Input::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Number of servings")
+                    .default(1.0)
+                    .interact_text()?;
+                let mut food = product.portion(serving_grams * servings);
+                food.unit = "serving".to_string();
+                (food, servings)
+            } else {
+                let grams = read_grams()?;
+                (product.portion(grams), grams)
+            }
+        }
+        None => {
+            println!(
+                "  {}",
+                "No serving size is known for this product.".dimmed()
+            );
+            let grams = read_grams()?;
+            (product.portion(grams), grams)
+        }
+    };
+
+    let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "Log {} {} of {} ({:.0} calories)?",
+            quantity, food.unit, food.name, food.calories
+        ))
+        .default(true)
+        .interact()?;
+    if confirmed {
+        app.add_food(food, quantity)?;
+        println!("\n{}", "✅ Food added successfully!".green());
+    }
+    Ok(())
+}
+
+fn read_grams() -> AppResult<f64> {
+    Ok(Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Amount in grams")
+        .default(100.0)
+        .interact_text()?)
+}
+
+/// Lets the user accept, edit or reject each search result before anything
+/// is logged. Returns the accepted results.
+fn review_nutrition(results: Vec<NutritionInfo>) -> AppResult<Vec<NutritionInfo>> {
+    let total = results.len();
+    let mut accepted = Vec::new();
+    for (index, mut info) in results.into_iter().enumerate() {
+        loop {
+            println!(
+                "\n{} {} {} {}",
+                format!("[{}/{}]", index + 1, total).cyan(),
+                info.quantity.to_string().yellow(),
+                info.unit.cyan(),
+                info.name.green()
+            );
+            println!(
+                "    Calories: {}, Protein: {}g, Fat: {}g, Carbs: {}g",
+                info.calories.to_string().red(),
+                info.protein.to_string().blue(),
+                info.fat.to_string().magenta(),
+                info.carbs.to_string().yellow()
+            );
+
+            let choices = vec!["✅ Accept", "✏️ Edit", "❌ Reject"];
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Log this food?")
+                .default(0)
+                .items(&choices)
+                .interact()?;
+            match selection {
+                0 => {
+                    accepted.push(info);
+                    break;
+                }
+                1 => info = edit_nutrition(info)?,
+                2 => break,
+                _ => unreachable!(),
+            }
+        }
+    }
+    Ok(accepted)
 }
 
-fn add_food(app: &mut App) -> AppResult<()> {
-    let choices = vec![
-        "✏️ Add food manually",
-        "🌐 Search and add food from API",
-        "⬅️ Back to main menu",
-    ];
+/// Prompts for the amounts of a search result, as `edit_amounts` does.
+fn edit_nutrition(info: NutritionInfo) -> AppResult<NutritionInfo> {
+    let food = edit_amounts(&ColorfulTheme::default(), &info.to_food())?;
+    Ok(NutritionInfo {
+        name: info.name,
+        quantity: food.quantity,
+        unit: food.unit,
+        calories: food.calories,
+        protein: food.protein,
+        fat: food.fat,
+        carbs: food.carbs,
+    })
+}
 
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Choose an option")
-        .default(0)
-        .items(&choices)
-        .interact()?;
+/// Prompts for the quantity, unit, macros and calories of `food`. Changing
+/// the quantity scales the suggested values to match, and changing the
+/// macros suggests the calories they add up to.
+fn edit_amounts(theme: &ColorfulTheme, food: &Food) -> AppResult<Food> {
+    let quantity: f64 = Input::with_theme(theme)
+        .with_prompt("Quantity")
+        .default(food.quantity)
+        .interact_text()?;
+    let unit: String = Input::with_theme(theme)
+        .with_prompt("Unit")
+        .default(food.unit.clone())
+        .interact_text()?;
+    let scaled = food.with_quantity(quantity);
+    let round = |value: f64| (value * 10.0).round() / 10.0;
+    let protein: f64 = Input::with_theme(theme)
+        .with_prompt("Protein (grams)")
+        .default(round(scaled.protein))
+        .interact_text()?;
+    let fat: f64 = Input::with_theme(theme)
+        .with_prompt("Fat (grams)")
+        .default(round(scaled.fat))
+        .interact_text()?;
+    let carbs: f64 = Input::with_theme(theme)
+        .with_prompt("Carbohydrates (grams)")
+        .default(round(scaled.carbs))
+        .interact_text()?;
+    let mut edited = Food {
+        unit,
+        protein,
+        fat,
+        carbs,
+        ..scaled.clone()
+    };
+    let macros_changed = (protein, fat, carbs)
+        != (
+            round(scaled.protein),
+            round(scaled.fat),
+            round(scaled.carbs),
+        );
+    let calories = if macros_changed {
+        edited.macro_calories()
+    } else {
+        scaled.calories
+    };
+    edited.calories = Input::with_theme(theme)
+        .with_prompt("Calories")
+        .default(round(calories))
+        .interact_text()?;
+    Ok(edited)
+}
 
-    match selection {
-        0 => add_food_manually(app),
-        1 => search_and_add_food(app),
-        2 => Ok(()),
-        _ => unreachable!(),
-    }
+/// What the user picked while typing a food.
+enum Suggestion {
+    Library(LibraryFood),
+    /// A name suggested by a provider, or the text as typed.
+    Query(String),
 }
 
-fn add_food_manually(app: &mut App) -> AppResult<()> {
-    let name: String = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Enter food name")
-        .interact_text()?;
+/// Wait after the last key press before asking a provider for suggestions.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+const MAX_SUGGESTIONS: usize = 8;
 
-    let quantity: f64 = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Enter quantity (e.g., 1, 2, 0.5)")
-        .interact_text()?;
+/// Leaves raw mode when dropped, also on errors.
+struct RawMode;
 
-    let protein: f64 = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Enter protein content (grams)")
-        .interact_text()?;
+impl RawMode {
+    fn enable() -> AppResult<Self> {
+        terminal::enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
 
-    let fat: f64 = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Enter fat content (grams)")
-        .interact_text()?;
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
 
-    let carbs: f64 = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Enter carbohydrate content (grams)")
-        .interact_text()?;
+/// Reads a food query, suggesting matches from the food library as the user
+/// types and, once typing pauses, from the providers. Up and down select a
+/// suggestion, Enter takes it (or the typed text) and Esc cancels. Falls
+/// back to a plain prompt when the terminal does not support raw mode.
+fn type_food(app: &mut App, prompt: &str) -> AppResult<Option<Suggestion>> {
+    let _raw = match RawMode::enable() {
+        Ok(raw) => raw,
+        Err(_) => {
+            let query: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(prompt)
+                .interact_text()?;
+            return Ok(Some(Suggestion::Query(query)));
+        }
+    };
 
-    // Calculate calories
-    let calories = (protein * 4.0) + (fat * 9.0) + (carbs * 4.0);
+    let mut input = String::new();
+    let mut library: Vec<LibraryFood> = Vec::new();
+    let mut online: Vec<String> = Vec::new();
+    let mut online_query = String::new();
+    let mut selected: Option<usize> = None;
+    let mut last_edit: Option<Instant> = None;
+    let mut stdout = io::stdout();
+
+    let picked = loop {
+        let count = library.len() + online.len();
+        draw_suggestions(&mut stdout, prompt, &input, &library, &online, selected)?;
+
+        let wait = match last_edit {
+            Some(at) => DEBOUNCE.saturating_sub(at.elapsed()),
+            None => Duration::from_secs(3600),
+        };
+        if !event::poll(wait)? {
+            // Typing has paused: ask the providers.
+            last_edit = None;
+            if input.trim().len() >= 2 && input != online_query {
+                online = app.suggest_foods(input.trim()).unwrap_or_default();
+                online.retain(|name| {
+                    !library
+                        .iter()
+                        .any(|f| f.food.name.eq_ignore_ascii_case(name))
+                });
+                online.truncate(MAX_SUGGESTIONS.saturating_sub(library.len()));
+                online_query = input.clone();
+                selected = None;
+            }
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        match key.code {
+            KeyCode::Esc => break None,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break None,
+            KeyCode::Enter => match selected {
+                Some(index) if index < library.len() => {
+                    break Some(Suggestion::Library(library[index].clone()))
+                }
+                Some(index) => {
+                    break Some(Suggestion::Query(online[index - library.len()].clone()))
+                }
+                None if input.trim().is_empty() => continue,
+                None => break Some(Suggestion::Query(input.trim().to_string())),
+            },
+            KeyCode::Down if count > 0 => {
+                selected = Some(selected.map_or(0, |index| (index + 1) % count));
+            }
+            KeyCode::Up if count > 0 => {
+                selected = Some(selected.map_or(count - 1, |index| (index + count - 1) % count));
+            }
+            KeyCode::Char(c) => {
+                input.push(c);
+                last_edit = Some(Instant::now());
+            }
+            KeyCode::Backspace => {
+                input.pop();
+                last_edit = Some(Instant::now());
+            }
+            _ => {}
+        }
+        if last_edit.is_some() {
+            library = if input.trim().is_empty() {
+                Vec::new()
+            } else {
+                app.search_library(input.trim(), MAX_SUGGESTIONS / 2)?
+                    .into_iter()
+                    .map(|(food, _)| food)
+                    .collect()
+            };
+            online.clear();
+            online_query.clear();
+            selected = None;
+        }
+    };
 
-    let food = Food::new(&name, quantity, "serving", protein, fat, carbs, calories);
-    app.add_food_manually(food)?;
+    queue!(
+        stdout,
+        cursor::MoveToColumn(0),
+        terminal::Clear(ClearType::FromCursorDown)
+    )?;
+    stdout.flush()?;
+    drop(_raw);
+    match &picked {
+        Some(Suggestion::Library(food)) => println!("{} {}", prompt.bold(), food.food.name),
+        Some(Suggestion::Query(query)) => println!("{} {}", prompt.bold(), query),
+        None => println!(),
+    }
+    Ok(picked)
+}
 
-    println!("\n{}", "✅ Food added successfully!".green());
-    pause()?;
+/// Redraws the input line and the suggestions below it, leaving the cursor
+/// at the end of the input.
+fn draw_suggestions(
+    stdout: &mut io::Stdout,
+    prompt: &str,
+    input: &str,
+    library: &[LibraryFood],
+    online: &[String],
+    selected: Option<usize>,
+) -> AppResult<()> {
+    queue!(
+        stdout,
+        cursor::MoveToColumn(0),
+        terminal::Clear(ClearType::FromCursorDown)
+    )?;
+    write!(stdout, "{} {}", prompt.bold(), input)?;
+    let labels = library
+        .iter()
+        .map(|food| format!("📚 {}", food.food.name))
+        .chain(online.iter().map(|name| format!("🌐 {}", name)));
+    // Long names would wrap and throw off the cursor movement below.
+    let width = match terminal::size() {
+        Ok((columns, _)) if columns > 0 => columns as usize,
+        _ => 80,
+    };
+    let mut lines = 0;
+    for (index, label) in labels.enumerate() {
+        let label: String = label.chars().take(width.saturating_sub(4)).collect();
+        let line = if selected == Some(index) {
+            format!("> {}", label).cyan().bold().to_string()
+        } else {
+            format!("  {}", label)
+        };
+        write!(stdout, "\r\n{}", line)?;
+        lines += 1;
+    }
+    if lines > 0 {
+        queue!(stdout, cursor::MoveUp(lines))?;
+    }
+    let column = prompt.chars().count() + 1 + input.chars().count();
+    queue!(stdout, cursor::MoveToColumn(column as u16))?;
+    stdout.flush()?;
     Ok(())
 }
 
 fn search_and_add_food(app: &mut App) -> AppResult<()> {
-    let query: String = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Enter food and quantity (e.g., '2 apples, 200 grams of chicken') ")
-        .interact_text()?;
+    let prompt = "Enter food and quantity (e.g., '2 apples, 200 grams of chicken'):";
+    let query = match type_food(app, prompt)? {
+        None => return Ok(()),
+        Some(Suggestion::Library(food)) => {
+            let grams = read_grams()?;
+            app.add_food(food.portion(grams), grams)?;
+            println!("\n{}", "✅ Food added successfully!".green());
+            pause()?;
+            return Ok(());
+        }
+        Some(Suggestion::Query(query)) => query,
+    };
 
-    match app.search_and_add_food(&query) {
-        Ok(added_foods) => {
-            if added_foods.is_empty() {
-                println!("\n{}", "❌ No foods found matching the query.".yellow());
-                println!("{}", "You can try adding the food manually.".yellow());
-            } else {
-                println!("\n{}", "✅ Foods added successfully:".green());
-                for food in added_foods {
-                    println!(
-                        "  • {} {} {}",
-                        food.quantity.to_string().yellow(),
-                        food.unit.cyan(),
-                        food.name.green()
-                    );
-                    println!(
-                        "    Calories: {}, Protein: {}g, Fat: {}g, Carbs: {}g",
-                        food.calories().to_string().red(),
-                        food.protein_content().to_string().blue(),
-                        food.fat.to_string().magenta(),
-                        food.carbs.to_string().yellow()
-                    );
+    let results = match app.search_nutrition(&query) {
+        Ok(results) => results,
+        Err(e) if is_offline(e.as_ref()) => {
+            println!("\n{}", format!("⚠️ {}", e).yellow());
+            let parsed = app.parse_foods(&query)?;
+            if parsed.foods.is_empty() {
+                if is_not_configured(e.as_ref()) {
+                    println!("{}", "Enter the food manually instead.".yellow());
+                    return add_food_manually(app);
                 }
+                print_api_error_hint(e.as_ref());
+                println!("{}", "You can try adding the food manually.".yellow());
+                pause()?;
+                return Ok(());
+            }
+            println!("{}", "Using the offline food library instead.".yellow());
+            for item in &parsed.unmatched {
+                println!("  {} {}", "Not in the library:".yellow(), item);
             }
+            parsed.foods
         }
         Err(e) => {
-            println!("\n{}", format!("❌ Error adding food: {}", e).red());
+            println!("\n{}", format!("❌ Error searching for food: {}", e).red());
+            print_api_error_hint(e.as_ref());
             println!("{}", "You can try adding the food manually.".yellow());
+            pause()?;
+            return Ok(());
+        }
+    };
+
+    if results.is_empty() {
+        println!("\n{}", "❌ No foods found matching the query.".yellow());
+        println!("{}", "You can try adding the food manually.".yellow());
+    } else {
+        let accepted = review_nutrition(results)?;
+        let added_foods = app.add_nutrition(&accepted, None)?;
+        if added_foods.is_empty() {
+            println!("\n{}", "Nothing was added.".yellow());
+        } else {
+            println!("\n{}", "✅ Foods added successfully:".green());
+        }
+        for food in added_foods {
+            println!(
+                "  • {} {} {}",
+                food.quantity.to_string().yellow(),
+                food.unit.cyan(),
+                food.name.green()
+            );
+            println!(
+                "    Calories: {}, Protein: {}g, Fat: {}g, Carbs: {}g",
+                food.calories().to_string().red(),
+                food.protein_content().to_string().blue(),
+                food.fat.to_string().magenta(),
+                food.carbs.to_string().yellow()
+            );
         }
     }
 
@@ -239,6 +1236,30 @@ fn search_and_add_food(app: &mut App) -> AppResult<()> {
     Ok(())
 }
 
+fn is_not_configured(e: &(dyn Error + Send + Sync + 'static)) -> bool {
+    matches!(
+        e.downcast_ref::<ApiError>(),
+        Some(ApiError::NotConfigured(_))
+    )
+}
+
+fn is_offline(e: &(dyn Error + Send + Sync + 'static)) -> bool {
+    e.downcast_ref::<ApiError>()
+        .is_some_and(ApiError::is_offline)
+}
+
+/// Suggests what to do about a failed provider request.
+fn print_api_error_hint(e: &(dyn Error + Send + Sync + 'static)) {
+    let hint = match e.downcast_ref::<ApiError>() {
+        Some(ApiError::Network(_)) => "Check your internet connection and try again.",
+        Some(ApiError::Auth(_)) => "Check the API keys in the config file or environment.",
+        Some(ApiError::Quota(_)) => "The provider's request limit is reached; try again later.",
+        Some(ApiError::Parse(_)) => "The provider sent an unexpected response; try another one.",
+        _ => return,
+    };
+    println!("{}", hint.yellow());
+}
+
 fn remove_food(app: &mut App) -> AppResult<()> {
     loop {
         let day = app.get_current_day()?;
@@ -268,35 +1289,218 @@ fn remove_food(app: &mut App) -> AppResult<()> {
     Ok(())
 }
 
+/// Edits a food logged on the current or any other day.
+fn edit_food(app: &mut App) -> AppResult<()> {
+    let current_date = app.get_current_day()?.date;
+    let date = input_date("Day (YYYY-MM-DD)", Some(current_date))?;
+    app.load_range(date, date)?;
+    let foods = match app.days_in_range(date, date).next() {
+        Some(day) if !day.foods.is_empty() => day.foods.clone(),
+        _ => {
+            println!("\n{}", format!("No foods logged on {}.", date).yellow());
+            pause()?;
+            return Ok(());
+        }
+    };
+
+    let mut choices: Vec<String> = foods
+        .iter()
+        .enumerate()
+        .map(|(i, food)| {
+            format!(
+                "{}. {} {} {} ({:.0} calories)",
+                i + 1,
+                food.quantity,
+                food.unit,
+                food.name,
+                food.calories()
+            )
+        })
+        .collect();
+    choices.push("⬅️ Go back".to_string());
+    let index = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select a food to edit")
+        .default(0)
+        .items(&choices)
+        .interact()?;
+    if index == foods.len() {
+        return Ok(());
+    }
+
+    let food = edit_food_fields(&foods[index])?;
+    app.edit_food(date, index, food)?;
+    let day = app
+        .days_in_range(date, date)
+        .next()
+        .ok_or("Day not found")?;
+    println!("\n{}", "✅ Food updated!".green());
+    println!(
+        "{}",
+        format!(
+            "🔢 {} now totals {:.0} calories and {:.1}g protein",
+            date,
+            day.total_calories(),
+            day.total_protein()
+        )
+        .yellow()
+    );
+    pause()?;
+    Ok(())
+}
+
+/// Prompts for each field of `food`, with the current values as defaults.
+fn edit_food_fields(food: &Food) -> AppResult<Food> {
+    let theme = ColorfulTheme::default();
+    let name: String = Input::with_theme(&theme)
+        .with_prompt("Name")
+        .default(food.name.clone())
+        .interact_text()?;
+    let mut edited = Food {
+        name,
+        ..edit_amounts(&theme, food)?
+    };
+
+    let meals = [
+        None,
+        Some(Meal::Breakfast),
+        Some(Meal::Lunch),
+        Some(Meal::Dinner),
+        Some(Meal::Snack),
+    ];
+    let meal_names: Vec<String> = meals
+        .iter()
+        .map(|meal| meal.map_or("No meal".to_string(), |meal| meal.to_string()))
+        .collect();
+    let meal = Select::with_theme(&theme)
+        .with_prompt("Meal")
+        .default(meals.iter().position(|&m| m == food.meal).unwrap_or(0))
+        .items(&meal_names)
+        .interact()?;
+    edited.meal = meals[meal];
+
+    let time: String = Input::with_theme(&theme)
+        .with_prompt("Time eaten (HH:MM, empty for none)")
+        .default(
+            food.time
+                .map_or(String::new(), |t| t.format("%H:%M").to_string()),
+        )
+        .allow_empty(true)
+        .validate_with(|input: &String| -> Result<(), &str> {
+            if input.trim().is_empty() || parse_time(input).is_ok() {
+                Ok(())
+            } else {
+                Err("Use HH:MM, e.g. 08:30")
+            }
+        })
+        .interact_text()?;
+    edited.time = parse_time(&time).ok();
+    Ok(edited)
+}
+
 fn view_workouts(app: &App) -> AppResult<()> {
     let day = app.get_current_day()?;
 
-    if let Some(workout) = &day.workout {
-        println!("\n{}", "💪 Today's Workout:".cyan());
-        println!(
-            "  Type: {}",
-            match workout.workout_type {
-                WorkoutType::WeightLifting => "Weight Lifting",
-                WorkoutType::Cardio => "Cardio",
-            }
-            .green()
-        );
-        println!(
-            "  Duration: {} minutes",
-            workout.duration.to_string().yellow()
-        );
+    if day.workouts.is_empty() {
+        println!("\n{}", "No workout recorded for today.".yellow());
+    } else {
+        println!("\n{}", "💪 Today's Workouts:".cyan());
+        for workout in &day.workouts {
+            print_workout(workout);
+        }
         println!(
-            "  Calories Burnt: {}",
-            workout.calories_burnt.to_string().red()
+            "  Total Calories Burnt: {}",
+            day.calories_burnt().to_string().red()
         );
-    } else {
-        println!("\n{}", "No workout recorded for today.".yellow());
     }
 
     pause()?;
     Ok(())
 }
 
+fn print_workout(workout: &Workout) {
+    println!("  • {}", workout.label().green());
+    println!(
+        "    Duration: {} minutes, Calories Burnt: {}",
+        workout.duration.to_string().yellow(),
+        workout.calories_burnt.to_string().red()
+    );
+}
+
+/// Logs workouts described in words, estimated by Nutritionix from the
+/// user's profile, after the user has confirmed them.
+fn describe_workout(app: &mut App) -> AppResult<()> {
+    let query: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("What did you do? (e.g., 'ran 5k in 30 min, 45 min yoga')")
+        .interact_text()?;
+
+    match app.search_exercise(&query) {
+        Ok(workouts) if workouts.is_empty() => {
+            println!("\n{}", "❌ No exercise recognized.".yellow());
+        }
+        Ok(workouts) => {
+            println!("\n{}", "💪 Recognized:".cyan());
+            for workout in &workouts {
+                print_workout(workout);
+            }
+            let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Log these workouts?")
+                .default(true)
+                .interact()?;
+            if confirmed {
+                for workout in workouts {
+                    app.add_workout(workout)?;
+                }
+                println!("\n{}", "✅ Workouts added successfully!".green());
+            }
+        }
+        Err(e) if is_not_configured(e.as_ref()) => {
+            println!("\n{}", format!("⚠️ {}", e).yellow());
+            println!("{}", "Enter the workout manually instead.".yellow());
+            return add_workout(app);
+        }
+        Err(e) => {
+            println!("\n{}", format!("❌ Error looking up exercise: {}", e).red());
+            print_api_error_hint(e.as_ref());
+        }
+    }
+    pause()?;
+    Ok(())
+}
+
+fn remove_workout(app: &mut App) -> AppResult<()> {
+    let day = app.get_current_day()?;
+    if day.workouts.is_empty() {
+        println!("\n{}", "No workout recorded for today.".yellow());
+        pause()?;
+        return Ok(());
+    }
+    let mut choices: Vec<String> = day
+        .workouts
+        .iter()
+        .map(|w| {
+            format!(
+                "{} ({} min, {} kcal)",
+                w.label(),
+                w.duration,
+                w.calories_burnt
+            )
+        })
+        .collect();
+    choices.push("⬅️ Back".to_string());
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select workout to remove")
+        .default(0)
+        .items(&choices)
+        .interact()?;
+    if selection < day.workouts.len() {
+        app.remove_workout(selection)?;
+        println!("\n{}", "✅ Workout removed.".green());
+        pause()?;
+    }
+    Ok(())
+}
+
 fn add_workout(app: &mut App) -> AppResult<()> {
     let workout_types = vec!["Weight Lifting", "Cardio"];
     let workout_type = Select::with_theme(&ColorfulTheme::default())
@@ -341,16 +1545,27 @@ fn add_workout(app: &mut App) -> AppResult<()> {
     Ok(())
 }
 
-fn reset_day(app: &mut App) -> AppResult<()> {
-    app.reset_day()?;
-    println!("\n{}", "✅ Day reset successfully!".green());
+fn undo(app: &mut App) -> AppResult<()> {
+    match app.undo()? {
+        Some(description) => println!("\n{}", format!("✅ Undone: {}", description).green()),
+        None => println!("\n{}", "Nothing to undo.".yellow()),
+    }
+    pause()?;
+    Ok(())
+}
+
+fn redo(app: &mut App) -> AppResult<()> {
+    match app.redo()? {
+        Some(description) => println!("\n{}", format!("✅ Redone: {}", description).green()),
+        None => println!("\n{}", "Nothing to redo.".yellow()),
+    }
     pause()?;
     Ok(())
 }
 
-fn register_day(app: &mut App) -> AppResult<()> {
-    app.register_day()?;
-    println!("\n{}", "✅ New day registered successfully!".green());
+fn reset_day(app: &mut App) -> AppResult<()> {
+    app.reset_day()?;
+    println!("\n{}", "✅ Day reset successfully!".green());
     pause()?;
     Ok(())
 }
@@ -364,9 +1579,14 @@ fn show_current_day(app: &App) -> AppResult<()> {
     );
     println!("\n{}", "🍽️ Foods consumed:".cyan());
     for food in &day.foods {
+        let occasion = food
+            .occasion()
+            .map(|occasion| format!(" ({})", occasion))
+            .unwrap_or_default();
         println!(
-            "  • {} ({:.0} calories)",
+            "  • {}{} ({:.0} calories)",
             food.name.green(),
+            occasion,
             food.calories()
         );
     }
@@ -374,11 +1594,35 @@ fn show_current_day(app: &App) -> AppResult<()> {
     Ok(())
 }
 
+fn show_change_log(app: &App) -> AppResult<()> {
+    let date = app.get_current_day()?.date;
+    let events: Vec<_> = app
+        .events()?
+        .into_iter()
+        .filter(|event| event.change.date() == Some(date))
+        .collect();
+
+    println!("\n{}", format!("📜 Changes to {}:", date).cyan());
+    if events.is_empty() {
+        println!("{}", "No changes recorded for this day.".yellow());
+    }
+    for event in events {
+        println!(
+            "  {} {}",
+            event.at.format("%Y-%m-%d %H:%M:%S").to_string().green(),
+            event.change
+        );
+    }
+    pause()?;
+    Ok(())
+}
+
 fn food_tracking_menu(app: &mut App) -> AppResult<()> {
     loop {
         let choices = vec![
             "➕ Add food",
             "➖ Remove food",
+            "✏️ Edit food",
             "🔍 Search food",
             "⬅️ Back to main menu",
         ];
@@ -392,7 +1636,8 @@ fn food_tracking_menu(app: &mut App) -> AppResult<()> {
         match selection {
             0 => add_food(app)?,
             1 => remove_food(app)?,
-            2 => {
+            2 => edit_food(app)?,
+            3 => {
                 let search_choices = vec![
                     "🍽️ Browse all foods",
                     "🔍 Search for a specific food",
@@ -412,7 +1657,7 @@ fn food_tracking_menu(app: &mut App) -> AppResult<()> {
                     _ => unreachable!(),
                 }
             }
-            3 => break,
+            4 => break,
             _ => unreachable!(),
         }
     }
@@ -431,7 +1676,7 @@ fn browse_foods(app: &mut App) -> AppResult<()> {
     }
 
     let mut sort_order = SortOrder::Alphabetical;
-    let total_pages = (all_foods.len() + ITEMS_PER_PAGE - 1) / ITEMS_PER_PAGE;
+    let total_pages = all_foods.len().div_ceil(ITEMS_PER_PAGE);
     let mut current_page = 1;
 
     loop {
@@ -505,7 +1750,7 @@ fn browse_foods(app: &mut App) -> AppResult<()> {
 
             // Ask for quantity
             let quantity: f64 = Input::with_theme(&ColorfulTheme::default())
-                .with_prompt(&format!("Enter quantity of {} to add", selected_food.name))
+                .with_prompt(format!("Enter quantity of {} to add", selected_food.name))
                 .default(1.0)
                 .interact_text()?;
 
@@ -655,7 +1900,7 @@ fn search_specific_food(app: &mut App) -> AppResult<()> {
             } else {
                 let selected_food = results[selection].0.clone();
                 let quantity: f64 = Input::with_theme(&ColorfulTheme::default())
-                    .with_prompt(&format!("Enter quantity of {} to add", selected_food.name))
+                    .with_prompt(format!("Enter quantity of {} to add", selected_food.name))
                     .default(1.0)
                     .interact_text()?;
 
@@ -668,21 +1913,19 @@ fn search_specific_food(app: &mut App) -> AppResult<()> {
 }
 
 fn change_day(app: &mut App) -> AppResult<()> {
-    let date_str: String = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Enter date (YYYY-MM-DD)")
-        .interact_text()?;
+    let date = input_date("Enter date (YYYY-MM-DD)", None)?;
+    app.change_day(date)?;
+    println!("\n{}", format!("✅ Changed to day: {}", date).green());
+    pause()?;
+    Ok(())
+}
 
-    match NaiveDate::parse_from_str(&date_str, "%Y-%m-%d") {
-        Ok(date) => {
-            app.change_day(date)?;
-            println!("\n{}", format!("✅ Changed to day: {}", date).green());
-        }
-        Err(_) => println!(
-            "\n{}",
-            "❌ Invalid date format. Please use YYYY-MM-DD.".red()
-        ),
+fn pick_day(app: &mut App) -> AppResult<()> {
+    let current = app.get_current_day()?.date;
+    if let Some(date) = calendar::pick_date(app, current)? {
+        app.change_day(date)?;
+        println!("{}", format!("✅ Changed to day: {}", date).green());
     }
-    pause()?;
     Ok(())
 }
 
@@ -699,9 +1942,9 @@ fn show_week_calories(app: &App) -> AppResult<()> {
         "\n{}",
         "📊 Calories, protein, and workouts in the last 7 days:".cyan()
     );
-    for &(date, calories, protein, workout) in &week_data {
-        let net_calories =
-            calories as i32 - workout.map_or(0, |w| w.calories_burnt) as i32 - bmr as i32;
+    for &(date, calories, protein, workouts) in &week_data {
+        let burnt: u32 = workouts.iter().map(|w| w.calories_burnt).sum();
+        let net_calories = calories as i32 - burnt as i32 - bmr as i32;
         let net_calories_str = if net_calories > 0 {
             format!("+{}", net_calories).red()
         } else {
@@ -714,15 +1957,11 @@ fn show_week_calories(app: &App) -> AppResult<()> {
             calories,
             protein
         );
-        if let Some(w) = workout {
+        for w in workouts {
             print!(
                 " (Workout: {} min {})",
                 w.duration.to_string().cyan(),
-                match w.workout_type {
-                    WorkoutType::WeightLifting => "Weight Lifting",
-                    WorkoutType::Cardio => "Cardio",
-                }
-                .magenta()
+                w.label().magenta()
             );
         }
 
@@ -792,12 +2031,66 @@ fn set_user_info(app: &mut App) -> AppResult<()> {
         _ => unreachable!(),
     };
 
-    app.set_user_info(height, weight, age, gender);
+    app.set_user_info(height, weight, age, gender)?;
     println!("\n{}", "✅ User information updated successfully!".green());
     pause()?;
     Ok(())
 }
 
+fn choose_providers(app: &mut App) -> AppResult<()> {
+    let path = config_path();
+    let mut config = Config::read(&path)?;
+
+    let names: Vec<&str> = Provider::ALL.iter().map(Provider::name).collect();
+    let enabled: Vec<bool> = Provider::ALL
+        .iter()
+        .map(|provider| config.providers.contains(provider))
+        .collect();
+    let chosen = MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Providers to use (space to toggle)")
+        .items(&names)
+        .defaults(&enabled)
+        .interact()?;
+
+    let mut providers: Vec<Provider> = chosen.iter().map(|&i| Provider::ALL[i]).collect();
+    providers.sort_by_key(|provider| {
+        config
+            .providers
+            .iter()
+            .position(|p| p == provider)
+            .unwrap_or(usize::MAX)
+    });
+    if providers.len() > 1 {
+        let names: Vec<&str> = providers.iter().map(Provider::name).collect();
+        let order = Sort::with_theme(&ColorfulTheme::default())
+            .with_prompt("Order to try them in (space to pick up and move)")
+            .items(&names)
+            .interact()?;
+        providers = order.iter().map(|&i| providers[i]).collect();
+    }
+
+    config.providers = providers;
+    config.save(&path)?;
+    app.set_config(Config::load(&path)?)?;
+
+    println!("\n{}", "✅ Nutrition providers saved.".green());
+    for provider in &app.config().providers {
+        if !app.config().is_configured(*provider) {
+            println!(
+                "{}",
+                format!(
+                    "⚠️ {} is skipped until its API key is set in {}",
+                    provider.name(),
+                    path
+                )
+                .yellow()
+            );
+        }
+    }
+    pause()?;
+    Ok(())
+}
+
 fn calculate_bmi(app: &App) -> AppResult<()> {
     let bmi = app.calculate_bmi();
     println!("\n{}", format!("Your BMI: {:.2}", bmi).cyan());
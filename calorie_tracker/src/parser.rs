@@ -0,0 +1,306 @@
+use crate::api::{round, NutritionInfo};
+use crate::library::{Library, LibraryFood};
+use fuzzy_matcher::skim::SkimMatcherV2;
+
+/// One food of a description such as "150g chicken breast", before it is
+/// matched to the library.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Portion {
+    pub quantity: f64,
+    /// The unit as written, such as "g" or "cups"; `None` for a count, as in
+    /// "2 eggs".
+    pub unit: Option<String>,
+    pub name: String,
+}
+
+/// The foods found in a description, and the parts that matched nothing.
+#[derive(Debug, Clone, Default)]
+pub struct ParsedFoods {
+    pub foods: Vec<NutritionInfo>,
+    pub unmatched: Vec<String>,
+}
+
+/// Units and their weight in grams. Volumes assume the density of water.
+const UNITS: [(&[&str], f64); 15] = [
+    (&["g", "gr", "gram", "grams", "gramme", "grammes"], 1.0),
+    (&["kg", "kilo", "kilos", "kilogram", "kilograms"], 1000.0),
+    (&["mg", "milligram", "milligrams"], 0.001),
+    (&["oz", "ounce", "ounces"], 28.35),
+    (&["lb", "lbs", "pound", "pounds"], 453.6),
+    (
+        &[
+            "ml",
+            "milliliter",
+            "milliliters",
+            "millilitre",
+            "millilitres",
+        ],
+        1.0,
+    ),
+    (&["l", "liter", "liters", "litre", "litres"], 1000.0),
+    (&["cup", "cups"], 240.0),
+    (&["tbsp", "tablespoon", "tablespoons"], 15.0),
+    (&["tsp", "teaspoon", "teaspoons"], 5.0),
+    (&["slice", "slices"], 30.0),
+    (&["glass", "glasses"], 250.0),
+    (&["bowl", "bowls"], 300.0),
+    (&["can", "cans"], 330.0),
+    (&["handful", "handfuls"], 30.0),
+];
+
+/// Units that count pieces, weighed like foods given without a unit.
+const PIECES: [&str; 6] = [
+    "piece", "pieces", "serving", "servings", "portion", "portions",
+];
+
+/// Typical weight in grams of one piece of common foods.
+const PIECE_WEIGHTS: [(&str, f64); 14] = [
+    ("egg", 50.0),
+    ("apple", 180.0),
+    ("banana", 120.0),
+    ("orange", 130.0),
+    ("pear", 180.0),
+    ("peach", 150.0),
+    ("tomato", 120.0),
+    ("potato", 170.0),
+    ("carrot", 60.0),
+    ("avocado", 200.0),
+    ("bread", 30.0),
+    ("toast", 30.0),
+    ("cookie", 15.0),
+    ("sausage", 75.0),
+];
+
+/// Weight of one piece when the food is not in `PIECE_WEIGHTS`.
+const DEFAULT_PIECE_WEIGHT: f64 = 100.0;
+
+const NUMBER_WORDS: [(&str, f64); 17] = [
+    ("a", 1.0),
+    ("an", 1.0),
+    ("one", 1.0),
+    ("two", 2.0),
+    ("three", 3.0),
+    ("four", 4.0),
+    ("five", 5.0),
+    ("six", 6.0),
+    ("seven", 7.0),
+    ("eight", 8.0),
+    ("nine", 9.0),
+    ("ten", 10.0),
+    ("twelve", 12.0),
+    ("half", 0.5),
+    ("quarter", 0.25),
+    ("couple", 2.0),
+    ("dozen", 12.0),
+];
+
+/// Splits a description into its foods at commas, semicolons, plus signs,
+/// ampersands, line breaks and the word "and".
+pub fn split_items(text: &str) -> Vec<String> {
+    text.split([',', ';', '+', '&', '\n'])
+        .flat_map(|part| part.split(" and "))
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Parses a number such as "2", "1.5", "1/2" or "½".
+fn parse_number(token: &str) -> Option<f64> {
+    match token {
+        "½" => return Some(0.5),
+        "¼" => return Some(0.25),
+        "¾" => return Some(0.75),
+        _ => {}
+    }
+    if let Some((numerator, denominator)) = token.split_once('/') {
+        let denominator: f64 = denominator.parse().ok()?;
+        return (denominator != 0.0).then_some(numerator.parse::<f64>().ok()? / denominator);
+    }
+    token.parse().ok().filter(|n: &f64| n.is_finite())
+}
+
+fn unit_grams(unit: &str) -> Option<f64> {
+    UNITS
+        .iter()
+        .find(|(names, _)| names.contains(&unit))
+        .map(|&(_, grams)| grams)
+}
+
+fn is_unit(word: &str) -> bool {
+    unit_grams(word).is_some() || PIECES.contains(&word)
+}
+
+/// Parses one food such as "150g chicken breast", "a cup of rice" or
+/// "2 eggs". A food without a quantity counts once.
+pub fn parse_portion(item: &str) -> Option<Portion> {
+    let lower = item.to_lowercase();
+    let mut words: Vec<&str> = lower.split_whitespace().collect();
+    let mut quantity = None;
+    let mut unit = None;
+
+    if let Some(first) = words.first().copied() {
+        // A number with the unit attached, such as "150g".
+        let split = first
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '/'))
+            .unwrap_or(first.len());
+        let (number, rest) = first.split_at(split);
+        if let Some(n) = parse_number(first) {
+            quantity = Some(n);
+            words.remove(0);
+        } else if let (Some(n), true) = (parse_number(number), is_unit(rest)) {
+            quantity = Some(n);
+            unit = Some(rest.to_string());
+            words.remove(0);
+        } else if let Some(&(_, n)) = NUMBER_WORDS.iter().find(|(word, _)| *word == first) {
+            quantity = Some(n);
+            words.remove(0);
+        }
+    }
+    // "1 1/2 cups", "a dozen eggs", "a couple of bananas", "half a cup"
+    if let (Some(whole), Some(next)) = (quantity, words.first().copied()) {
+        if let Some(fraction) = next.contains('/').then(|| parse_number(next)).flatten() {
+            quantity = Some(whole + fraction);
+            words.remove(0);
+        } else if next == "dozen" {
+            quantity = Some(whole * 12.0);
+            words.remove(0);
+        } else if matches!(next, "a" | "an") && whole == 0.5 {
+            words.remove(0);
+        }
+    }
+    if unit.is_none() {
+        if let Some(word) = words.first().copied().filter(|word| is_unit(word)) {
+            unit = Some(word.to_string());
+            words.remove(0);
+        }
+    }
+    if words.first() == Some(&"of") {
+        words.remove(0);
+    }
+
+    let name = words.join(" ");
+    if name.is_empty() {
+        return None;
+    }
+    Some(Portion {
+        quantity: quantity.unwrap_or(1.0),
+        unit,
+        name,
+    })
+}
+
+/// The singular of the last word, e.g. "boiled eggs" to "boiled egg".
+fn singular(name: &str) -> Option<String> {
+    let (rest, last) = match name.rsplit_once(' ') {
+        Some((rest, last)) => (format!("{} ", rest), last),
+        None => (String::new(), name),
+    };
+    let singular = if let Some(stem) = last.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if let Some(stem) = last
+        .strip_suffix("oes")
+        .or_else(|| last.strip_suffix("ches"))
+        .or_else(|| last.strip_suffix("shes"))
+    {
+        format!("{}{}", stem, &last[stem.len()..last.len() - 2])
+    } else if last.ends_with('s') && !last.ends_with("ss") {
+        last[..last.len() - 1].to_string()
+    } else {
+        return None;
+    };
+    Some(rest + &singular)
+}
+
+/// Whether `a` and `b` name the same unit, such as "g" and "grams" or
+/// "slice" and "slices".
+fn same_unit(a: &str, b: &str) -> bool {
+    let group = |unit: &str| UNITS.iter().position(|(names, _)| names.contains(&unit));
+    a == b
+        || singular(a).as_deref() == Some(b)
+        || singular(b).as_deref() == Some(a)
+        || group(a).is_some() && group(a) == group(b)
+}
+
+impl Portion {
+    /// The name and, for a plural, its singular, as a provider may list the
+    /// food.
+    pub fn names(&self) -> Vec<String> {
+        let mut names = vec![self.name.clone()];
+        names.extend(singular(&self.name));
+        names
+    }
+
+    /// Whether the portion is measured in `unit`, as a provider gave it: the
+    /// same unit by any of its names, or a count of a unit that is not a
+    /// weight or volume, such as "large" for eggs.
+    pub fn is_in(&self, unit: &str) -> bool {
+        let unit = unit.to_lowercase();
+        match &self.unit {
+            Some(own) => same_unit(own, &unit),
+            None => unit_grams(&unit).is_none(),
+        }
+    }
+
+    /// Estimated weight in grams.
+    pub fn grams(&self) -> f64 {
+        match self.unit.as_deref().and_then(unit_grams) {
+            Some(grams) => self.quantity * grams,
+            None => {
+                let name = singular(&self.name).unwrap_or_else(|| self.name.clone());
+                let piece = PIECE_WEIGHTS
+                    .iter()
+                    .find(|(food, _)| name.split_whitespace().any(|word| word == *food))
+                    .map_or(DEFAULT_PIECE_WEIGHT, |&(_, grams)| grams);
+                self.quantity * piece
+            }
+        }
+    }
+
+    /// The best match in `library`, also trying the singular of the name.
+    pub fn find<'a>(
+        &self,
+        library: &'a Library,
+        matcher: &SkimMatcherV2,
+    ) -> Option<&'a LibraryFood> {
+        let best = |name: &str| library.search(matcher, name).into_iter().next();
+        let found = best(&self.name);
+        let singular = singular(&self.name).and_then(|name| best(&name));
+        match (found, singular) {
+            (Some(a), Some(b)) => Some(if b.1 > a.1 { b.0 } else { a.0 }),
+            (found, singular) => found.or(singular).map(|(food, _)| food),
+        }
+    }
+
+    /// `food` scaled to this portion, in the shape the providers return.
+    pub fn nutrition(&self, food: &LibraryFood) -> NutritionInfo {
+        let grams = self.grams();
+        let scaled = food.portion(grams);
+        NutritionInfo {
+            name: food.food.name.clone(),
+            quantity: self.quantity,
+            unit: self.unit.clone().unwrap_or_else(|| "piece".to_string()),
+            calories: round(scaled.calories),
+            protein: round(scaled.protein),
+            fat: round(scaled.fat),
+            carbs: round(scaled.carbs),
+        }
+    }
+}
+
+/// Understands descriptions such as "2 eggs, 150g chicken breast and a cup
+/// of rice" without a provider, by matching each food against `library`.
+pub fn parse_foods(text: &str, library: &Library, matcher: &SkimMatcherV2) -> ParsedFoods {
+    let mut parsed = ParsedFoods::default();
+    for item in split_items(text) {
+        let found = parse_portion(&item).and_then(|portion| {
+            portion
+                .find(library, matcher)
+                .map(|food| portion.nutrition(food))
+        });
+        match found {
+            Some(food) => parsed.foods.push(food),
+            None => parsed.unmatched.push(item),
+        }
+    }
+    parsed
+}
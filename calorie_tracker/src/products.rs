@@ -0,0 +1,327 @@
+use crate::food::Food;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+const KEY_LEN: usize = 20;
+const RECORD_LEN: usize = KEY_LEN + 8;
+
+/// A packaged product, with nutrient values per 100 g.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Product {
+    pub barcode: String,
+    pub name: String,
+    pub brand: Option<String>,
+    pub protein: f64,
+    pub fat: f64,
+    pub carbs: f64,
+    pub calories: f64,
+    /// Serving size as printed on the package, such as "30 g".
+    pub serving_size: Option<String>,
+    pub serving_grams: Option<f64>,
+}
+
+impl Product {
+    /// Name and brand, as shown in the day's food list.
+    pub fn display_name(&self) -> String {
+        match &self.brand {
+            Some(brand) if !brand.is_empty() => format!("{} ({})", self.name, brand),
+            _ => self.name.clone(),
+        }
+    }
+
+    /// The product with its values scaled to `grams`.
+    pub fn portion(&self, grams: f64) -> Food {
+        let factor = grams / 100.0;
+        Food::new(
+            &self.display_name(),
+            grams,
+            "g",
+            self.protein * factor,
+            self.fat * factor,
+            self.carbs * factor,
+            self.calories * factor,
+        )
+    }
+
+    /// One serving, if the serving size is known.
+    pub fn per_serving(&self) -> Option<Food> {
+        self.serving_grams.map(|grams| {
+            let mut food = self.portion(grams);
+            food.quantity = 1.0;
+            food.unit = "serving".to_string();
+            food
+        })
+    }
+}
+
+/// Checks the check digit of an EAN-8, UPC-A, EAN-13 or GTIN-14 code.
+pub fn is_valid_barcode(barcode: &str) -> bool {
+    let digits: Option<Vec<u32>> = barcode.chars().map(|c| c.to_digit(10)).collect();
+    let digits = match digits {
+        Some(digits) if [8, 12, 13, 14].contains(&digits.len()) => digits,
+        _ => return false,
+    };
+    let (check, body) = digits.split_last().unwrap();
+    // Weights alternate 3, 1, 3, ... from the digit next to the check digit.
+    let sum: u32 = body
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, digit)| if i % 2 == 0 { digit * 3 } else { *digit })
+        .sum();
+    (10 - sum % 10) % 10 == *check
+}
+
+/// Normalizes a barcode for lookups: UPC-A, EAN-13 and GTIN-14 codes for the
+/// same product differ only in leading zeros. Returns `None` for codes that
+/// cannot be indexed.
+pub fn barcode_key(barcode: &str) -> Option<[u8; KEY_LEN]> {
+    let trimmed = barcode.trim().trim_start_matches('0');
+    if barcode.trim().is_empty()
+        || trimmed.len() > KEY_LEN
+        || !trimmed.bytes().all(|b| b.is_ascii_alphanumeric())
+    {
+        return None;
+    }
+    let mut key = [b'0'; KEY_LEN];
+    key[KEY_LEN - trimmed.len()..].copy_from_slice(trimmed.as_bytes());
+    Some(key)
+}
+
+/// Products kept on disk so that large imports do not have to be loaded into
+/// memory. The directory holds:
+///
+/// - `products.jsonl`: one product per line;
+/// - `barcodes.idx`: fixed-size records of barcode key and line offset,
+///   sorted by key for binary search;
+/// - `names.tsv`: the lowercased name and brand with the line offset of each
+///   product, scanned for name searches.
+///
+/// The data is public, so it is never encrypted.
+pub struct ProductStore {
+    dir: PathBuf,
+}
+
+impl ProductStore {
+    pub fn new(dir: &str) -> Self {
+        Self {
+            dir: PathBuf::from(dir),
+        }
+    }
+
+    fn products_path(&self) -> PathBuf {
+        self.dir.join("products.jsonl")
+    }
+
+    fn barcodes_path(&self) -> PathBuf {
+        self.dir.join("barcodes.idx")
+    }
+
+    fn names_path(&self) -> PathBuf {
+        self.dir.join("names.tsv")
+    }
+
+    pub fn exists(&self) -> bool {
+        self.products_path().exists()
+    }
+
+    /// Copies the store to `dir`, if there is anything to copy.
+    pub fn copy_to(&self, dir: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if !self.exists() {
+            return Ok(());
+        }
+        fs::create_dir_all(dir)?;
+        let target = ProductStore::new(dir);
+        fs::copy(self.products_path(), target.products_path())?;
+        fs::copy(self.barcodes_path(), target.barcodes_path())?;
+        fs::copy(self.names_path(), target.names_path())?;
+        Ok(())
+    }
+
+    /// Number of stored products.
+    pub fn len(&self) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        match fs::metadata(self.barcodes_path()) {
+            Ok(metadata) => Ok(metadata.len() / RECORD_LEN as u64),
+            Err(_) => Ok(0),
+        }
+    }
+
+    pub fn is_empty(&self) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Starts adding products, which replace stored products with the same
+    /// barcode. Nothing changes until `ProductWriter::finish` is called.
+    pub fn writer(&self) -> Result<ProductWriter<'_>, Box<dyn Error + Send + Sync>> {
+        fs::create_dir_all(&self.dir)?;
+        let tmp_path = self.dir.join("products.jsonl.tmp");
+        Ok(ProductWriter {
+            writer: BufWriter::new(File::create(&tmp_path)?),
+            tmp_path,
+            barcodes: HashSet::new(),
+            store: self,
+        })
+    }
+
+    /// Adds a single product, such as one fetched from a provider.
+    pub fn insert(&self, product: Product) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut writer = self.writer()?;
+        writer.add(&product)?;
+        writer.finish().map(|_| ())
+    }
+
+    fn build_indexes(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut reader = BufReader::new(File::open(self.products_path())?);
+        let mut names = BufWriter::new(File::create(self.names_path())?);
+        let mut records: Vec<([u8; KEY_LEN], u64)> = Vec::new();
+        let mut offset = 0u64;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                break;
+            }
+            let product: Product = serde_json::from_str(&line)?;
+            if let Some(key) = barcode_key(&product.barcode) {
+                records.push((key, offset));
+            }
+            let name = product.display_name().replace(['\t', '\n'], " ");
+            writeln!(names, "{}\t{}", name.to_lowercase(), offset)?;
+            offset += read as u64;
+        }
+        names.into_inner()?.sync_all()?;
+
+        records.sort();
+        let mut index = BufWriter::new(File::create(self.barcodes_path())?);
+        for (key, offset) in records {
+            index.write_all(&key)?;
+            index.write_all(&offset.to_le_bytes())?;
+        }
+        index.into_inner()?.sync_all()?;
+        Ok(())
+    }
+
+    fn read_product(&self, offset: u64) -> Result<Product, Box<dyn Error + Send + Sync>> {
+        let mut file = File::open(self.products_path())?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut line = String::new();
+        BufReader::new(file).read_line(&mut line)?;
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    /// Looks up a product by barcode with a binary search of the index.
+    pub fn find_barcode(
+        &self,
+        barcode: &str,
+    ) -> Result<Option<Product>, Box<dyn Error + Send + Sync>> {
+        let key = match barcode_key(barcode) {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        let mut index = match File::open(self.barcodes_path()) {
+            Ok(index) => index,
+            Err(_) => return Ok(None),
+        };
+        let (mut low, mut high) = (0u64, index.metadata()?.len() / RECORD_LEN as u64);
+        let mut record = [0u8; RECORD_LEN];
+        while low < high {
+            let middle = (low + high) / 2;
+            index.seek(SeekFrom::Start(middle * RECORD_LEN as u64))?;
+            index.read_exact(&mut record)?;
+            match record[..KEY_LEN].cmp(&key) {
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => {
+                    let offset = u64::from_le_bytes(record[KEY_LEN..].try_into()?);
+                    return Ok(Some(self.read_product(offset)?));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Products whose name or brand contains every word of `query`, best
+    /// fuzzy match first.
+    pub fn search(
+        &self,
+        matcher: &SkimMatcherV2,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<(Product, i64)>, Box<dyn Error + Send + Sync>> {
+        let names = match File::open(self.names_path()) {
+            Ok(names) => BufReader::new(names),
+            Err(_) => return Ok(Vec::new()),
+        };
+        let query = query.to_lowercase();
+        let words: Vec<&str> = query.split_whitespace().collect();
+        let mut hits: Vec<(i64, usize, u64)> = Vec::new();
+        for line in names.lines() {
+            let line = line?;
+            let Some((name, offset)) = line.rsplit_once('\t') else {
+                continue;
+            };
+            if !words.iter().all(|word| name.contains(word)) {
+                continue;
+            }
+            if let Some(score) = matcher.fuzzy_match(name, &query) {
+                hits.push((score, name.len(), offset.parse()?));
+            }
+        }
+        hits.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        hits.into_iter()
+            .take(limit)
+            .map(|(score, _, offset)| Ok((self.read_product(offset)?, score)))
+            .collect()
+    }
+}
+
+/// Streams new products into a `ProductStore`.
+pub struct ProductWriter<'a> {
+    store: &'a ProductStore,
+    writer: BufWriter<File>,
+    tmp_path: PathBuf,
+    barcodes: HashSet<[u8; KEY_LEN]>,
+}
+
+impl ProductWriter<'_> {
+    /// Adds a product. Products without a usable barcode and repeats of a
+    /// barcode already added are skipped.
+    pub fn add(&mut self, product: &Product) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if let Some(key) = barcode_key(&product.barcode) {
+            if self.barcodes.insert(key) {
+                serde_json::to_writer(&mut self.writer, product)?;
+                self.writer.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges in the stored products that were not replaced and rebuilds the
+    /// indexes. Returns the number of products added or replaced.
+    pub fn finish(mut self) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        if self.store.exists() {
+            let reader = BufReader::new(File::open(self.store.products_path())?);
+            for line in reader.lines() {
+                let line = line?;
+                let product: Product = serde_json::from_str(&line)?;
+                let replaced =
+                    barcode_key(&product.barcode).is_none_or(|key| self.barcodes.contains(&key));
+                if !replaced {
+                    self.writer.write_all(line.as_bytes())?;
+                    self.writer.write_all(b"\n")?;
+                }
+            }
+        }
+        self.writer.into_inner()?.sync_all()?;
+        fs::rename(&self.tmp_path, self.store.products_path())?;
+        self.store.build_indexes()?;
+        Ok(self.barcodes.len())
+    }
+}
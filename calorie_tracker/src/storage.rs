@@ -1,27 +1,541 @@
-use crate::day::Day;
+use crate::crypto::{is_encrypted, Cipher};
+use crate::day::{Day, Days};
+use chrono::{Datelike, NaiveDate};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
 use serde_json;
+use std::collections::BTreeSet;
 use std::error::Error;
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
 
-pub fn load_days(file_path: &str) -> Result<Vec<Day>, Box<dyn Error + Send + Sync>> {
+/// The days as of journal event `seq`, plus the journal byte `offset` just past
+/// that event, so loading only has to replay events written after it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub seq: u64,
+    pub offset: u64,
+    pub days: Vec<Day>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SnapshotFile {
+    Snapshot(Snapshot),
+    // Data files written before the journal existed are a bare list of days.
+    Legacy(Vec<Day>),
+}
+
+/// Reads a whole file, decrypting it when it is encrypted. Returns `None` if
+/// the file does not exist.
+pub fn read_data(
+    file_path: &str,
+    cipher: Option<&Cipher>,
+) -> Result<Option<Vec<u8>>, Box<dyn Error + Send + Sync>> {
     let path = Path::new(file_path);
-    if path.exists() {
-        let mut file = File::open(path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        let days: Vec<Day> = serde_json::from_str(&contents)?;
-        Ok(days)
-    } else {
-        Ok(Vec::new())
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut file = File::open(path)?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    if is_encrypted(&contents) {
+        let cipher = cipher
+            .ok_or_else(|| format!("{} is encrypted; a passphrase is required", file_path))?;
+        contents = cipher.decrypt(&contents)?;
     }
+    Ok(Some(contents))
 }
 
-pub fn save_days(file_path: &str, days: &[Day]) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let serialized = serde_json::to_string(days)?;
-    let mut file = File::create(file_path)?;
-    file.write_all(serialized.as_bytes())?;
+/// Writes a whole file, encrypting it when a cipher is given.
+pub fn write_data(
+    file_path: &str,
+    contents: &[u8],
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let encrypted;
+    let contents = match cipher {
+        Some(cipher) => {
+            encrypted = cipher.encrypt(contents)?;
+            &encrypted
+        }
+        None => contents,
+    };
+    // Write to a sibling file and rename it into place so readers never see a
+    // partially written data file.
+    let tmp_path = format!("{}.tmp", file_path);
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, file_path)?;
     Ok(())
 }
 
+/// Returns whether the file at `file_path` exists and is encrypted.
+pub fn is_file_encrypted(file_path: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    match fs::read(file_path) {
+        Ok(contents) => Ok(is_encrypted(&contents)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// All days in a single JSON file, with sidecar files next to it.
+    SingleFile,
+    /// A directory holding one pretty-printed `YYYY/MM/DD.json` file per day.
+    PerDay,
+}
+
+/// Journal position of a per-day snapshot, kept in `snapshot.json`.
+#[derive(Default, Serialize, Deserialize)]
+struct SnapshotPosition {
+    seq: u64,
+    offset: u64,
+}
+
+/// Location and layout of a data set: the days plus the journal, undo
+/// history, profile and lock files that go with them.
+#[derive(Debug, Clone)]
+pub struct Store {
+    path: String,
+    layout: Layout,
+}
+
+impl Store {
+    /// A store at `path`, which uses the per-day layout if it is a directory.
+    pub fn new(path: &str) -> Self {
+        let layout = if Path::new(path).is_dir() {
+            Layout::PerDay
+        } else {
+            Layout::SingleFile
+        };
+        Self::with_layout(path, layout)
+    }
+
+    pub fn with_layout(path: &str, layout: Layout) -> Self {
+        Self {
+            path: path.to_string(),
+            layout,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Number of journal events after which a fresh snapshot is written. Day
+    /// files are rewritten on every change so they always reflect the data.
+    pub fn snapshot_interval(&self) -> u64 {
+        match self.layout {
+            Layout::SingleFile => 100,
+            Layout::PerDay => 1,
+        }
+    }
+
+    fn file(&self, sidecar: &str, per_day: &str) -> String {
+        match self.layout {
+            Layout::SingleFile => format!("{}.{}", self.path, sidecar),
+            Layout::PerDay => Path::new(&self.path)
+                .join(per_day)
+                .to_string_lossy()
+                .into_owned(),
+        }
+    }
+
+    /// The file holding the snapshot; its encryption header identifies the key.
+    pub fn snapshot_path(&self) -> String {
+        match self.layout {
+            Layout::SingleFile => self.path.clone(),
+            Layout::PerDay => self.file("snapshot", "snapshot.json"),
+        }
+    }
+
+    pub fn journal_path(&self) -> String {
+        self.file("journal", "journal.jsonl")
+    }
+
+    pub fn history_path(&self) -> String {
+        self.file("history", "history.json")
+    }
+
+    pub fn profile_path(&self) -> String {
+        self.file("profile", "profile.json")
+    }
+
+    /// Foods imported from nutrition databases.
+    pub fn library_path(&self) -> String {
+        self.file("library", "library.json")
+    }
+
+    /// Responses of nutrition providers, kept to avoid repeated requests.
+    pub fn api_cache_path(&self) -> String {
+        self.file("cache", "cache.json")
+    }
+
+    /// Directory of the packaged product database.
+    pub fn products_dir(&self) -> String {
+        self.file("products", "products")
+    }
+
+    /// Directory of products fetched from nutrition providers.
+    pub fn products_cache_dir(&self) -> String {
+        Path::new(&self.products_dir())
+            .join("cache")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    pub fn lock(&self, mode: LockMode) -> Result<FileLock, Box<dyn Error + Send + Sync>> {
+        lock_file(&self.file("lock", ".lock"), mode)
+    }
+
+    /// A store next to this one in the same layout, where a complete copy is
+    /// written before it replaces this store's files.
+    pub fn staging(&self) -> Store {
+        Store::with_layout(&format!("{}.staging", self.path), self.layout)
+    }
+
+    /// The existing files holding the data set: snapshot, days, archives,
+    /// journal, undo history, profile, library and provider cache. Products
+    /// and the lock file are not included.
+    fn data_files(&self) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let mut files = vec![
+            self.snapshot_path(),
+            self.journal_path(),
+            self.history_path(),
+            self.profile_path(),
+            self.library_path(),
+            self.api_cache_path(),
+        ];
+        if self.layout == Layout::PerDay {
+            files.extend(self.day_files()?);
+        }
+        files.extend(
+            self.archived_years()?
+                .into_iter()
+                .map(|year| self.archive_path(year)),
+        );
+        files.retain(|file| Path::new(file).is_file());
+        Ok(files)
+    }
+
+    /// Moves the data files of `staged`, a complete copy of this store in the
+    /// same layout, over the files of this store. Files `staged` does not
+    /// have are removed, and so is `staged` itself.
+    pub fn replace_with(&self, staged: &Store) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let old_files = self.data_files()?;
+        let mut new_files = Vec::new();
+        for staged_file in staged.data_files()? {
+            let file = format!("{}{}", self.path, &staged_file[staged.path.len()..]);
+            if let Some(parent) = Path::new(&file).parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(&staged_file, &file)?;
+            new_files.push(file);
+        }
+        for file in old_files {
+            if !new_files.contains(&file) {
+                fs::remove_file(file)?;
+            }
+        }
+        staged.discard()
+    }
+
+    /// Removes the data files of this store, such as a staged copy that is
+    /// not going to be used.
+    pub fn discard(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match self.layout {
+            Layout::SingleFile => {
+                for file in self.data_files()? {
+                    fs::remove_file(file)?;
+                }
+                if Path::new(&self.archive_dir()).is_dir() {
+                    fs::remove_dir_all(self.archive_dir())?;
+                }
+            }
+            Layout::PerDay => {
+                if Path::new(&self.path).is_dir() {
+                    fs::remove_dir_all(&self.path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn exists(&self) -> bool {
+        Path::new(&self.snapshot_path()).exists()
+    }
+
+    pub fn is_encrypted(&self) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        is_file_encrypted(&self.snapshot_path())
+    }
+
+    /// Creates the directory of a per-day store, with a `.gitignore` so only
+    /// the day, profile and library files are tracked when it is versioned.
+    pub fn create(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.layout == Layout::PerDay {
+            fs::create_dir_all(&self.path)?;
+            let gitignore = Path::new(&self.path).join(".gitignore");
+            if !gitignore.exists() {
+                fs::write(
+                    gitignore,
+                    ".lock\n*.tmp\njournal.jsonl\nhistory.json\nsnapshot.json\nproducts/\ncache.json\n",
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load_snapshot(
+        &self,
+        cipher: Option<&Cipher>,
+    ) -> Result<Snapshot, Box<dyn Error + Send + Sync>> {
+        match self.layout {
+            Layout::SingleFile => match read_data(&self.path, cipher)? {
+                Some(contents) => {
+                    let snapshot = match serde_json::from_slice(&contents)? {
+                        SnapshotFile::Snapshot(snapshot) => snapshot,
+                        SnapshotFile::Legacy(days) => Snapshot {
+                            days,
+                            ..Snapshot::default()
+                        },
+                    };
+                    Ok(snapshot)
+                }
+                None => Ok(Snapshot::default()),
+            },
+            Layout::PerDay => {
+                let position: SnapshotPosition = match read_data(&self.snapshot_path(), cipher)? {
+                    Some(contents) => serde_json::from_slice(&contents)?,
+                    None => SnapshotPosition::default(),
+                };
+                let mut days = Vec::new();
+                for file_path in self.day_files()? {
+                    if let Some(contents) = read_data(&file_path, cipher)? {
+                        days.push(serde_json::from_slice(&contents)?);
+                    }
+                }
+                Ok(Snapshot {
+                    seq: position.seq,
+                    offset: position.offset,
+                    days,
+                })
+            }
+        }
+    }
+
+    /// Writes a snapshot of `days` at the given journal position, leaving out
+    /// the `archived` dates. In the per-day layout only the files for `changed`
+    /// dates are rewritten (or removed, if the day no longer exists or is
+    /// archived); `None` rewrites every file.
+    pub fn save_snapshot(
+        &self,
+        seq: u64,
+        offset: u64,
+        days: &Days,
+        archived: &BTreeSet<NaiveDate>,
+        changed: Option<&BTreeSet<NaiveDate>>,
+        cipher: Option<&Cipher>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match self.layout {
+            Layout::SingleFile => {
+                let snapshot = Snapshot {
+                    seq,
+                    offset,
+                    days: days
+                        .values()
+                        .filter(|day| !archived.contains(&day.date))
+                        .cloned()
+                        .collect(),
+                };
+                let serialized = serde_json::to_string(&snapshot)?;
+                write_data(&self.path, serialized.as_bytes(), cipher)
+            }
+            Layout::PerDay => {
+                let dates: Vec<NaiveDate> = match changed {
+                    Some(changed) => changed.iter().copied().collect(),
+                    None => {
+                        for file_path in self.day_files()? {
+                            fs::remove_file(file_path)?;
+                        }
+                        days.keys().copied().collect()
+                    }
+                };
+                for date in dates {
+                    let file_path = self.day_path(date);
+                    match days.get(&date).filter(|_| !archived.contains(&date)) {
+                        Some(day) => {
+                            if let Some(parent) = Path::new(&file_path).parent() {
+                                fs::create_dir_all(parent)?;
+                            }
+                            let serialized = serde_json::to_string_pretty(day)?;
+                            write_data(&file_path, serialized.as_bytes(), cipher)?;
+                        }
+                        None if Path::new(&file_path).exists() => fs::remove_file(file_path)?,
+                        None => {}
+                    }
+                }
+                let serialized = serde_json::to_string(&SnapshotPosition { seq, offset })?;
+                write_data(&self.snapshot_path(), serialized.as_bytes(), cipher)
+            }
+        }
+    }
+
+    /// Directory holding the compressed archive of each archived year.
+    pub fn archive_dir(&self) -> String {
+        self.file("archive", "archive")
+    }
+
+    pub fn archive_path(&self, year: i32) -> String {
+        Path::new(&self.archive_dir())
+            .join(format!("{}.json.gz", year))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Years that have an archive file.
+    pub fn archived_years(&self) -> Result<BTreeSet<i32>, Box<dyn Error + Send + Sync>> {
+        let mut years = BTreeSet::new();
+        let dir = self.archive_dir();
+        if !Path::new(&dir).is_dir() {
+            return Ok(years);
+        }
+        for entry in fs::read_dir(dir)? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if let Some(year) = name
+                .strip_suffix(".json.gz")
+                .and_then(|year| year.parse().ok())
+            {
+                years.insert(year);
+            }
+        }
+        Ok(years)
+    }
+
+    /// Reads the archived days of `year`, if any.
+    pub fn load_archive(
+        &self,
+        year: i32,
+        cipher: Option<&Cipher>,
+    ) -> Result<Vec<Day>, Box<dyn Error + Send + Sync>> {
+        match read_data(&self.archive_path(year), cipher)? {
+            Some(contents) => Ok(serde_json::from_reader(GzDecoder::new(&contents[..]))?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Replaces the archive of `year` with `days`, which must all fall in that
+    /// year. The archive file is removed when `days` is empty.
+    pub fn save_archive(
+        &self,
+        year: i32,
+        days: &[&Day],
+        cipher: Option<&Cipher>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        debug_assert!(days.iter().all(|day| day.date.year() == year));
+        let file_path = self.archive_path(year);
+        if days.is_empty() {
+            if Path::new(&file_path).exists() {
+                fs::remove_file(file_path)?;
+            }
+            return Ok(());
+        }
+        fs::create_dir_all(self.archive_dir())?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        serde_json::to_writer(&mut encoder, days)?;
+        write_data(&file_path, &encoder.finish()?, cipher)
+    }
+
+    fn day_path(&self, date: NaiveDate) -> String {
+        Path::new(&self.path)
+            .join(date.format("%Y").to_string())
+            .join(date.format("%m").to_string())
+            .join(date.format("%d.json").to_string())
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Paths of all `YYYY/MM/DD.json` files in a per-day store.
+    fn day_files(&self) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let mut files = Vec::new();
+        for year in numbered_entries(Path::new(&self.path))? {
+            for month in numbered_entries(&year)? {
+                for day in fs::read_dir(&month)? {
+                    let day = day?.path();
+                    let is_day_file = day.extension().is_some_and(|ext| ext == "json")
+                        && day
+                            .file_stem()
+                            .is_some_and(|stem| stem.to_string_lossy().parse::<u32>().is_ok());
+                    if is_day_file {
+                        files.push(day.to_string_lossy().into_owned());
+                    }
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+/// Subdirectories of `dir` whose names are numbers, such as years and months.
+fn numbered_entries(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error + Send + Sync>> {
+    let mut entries = Vec::new();
+    if !dir.is_dir() {
+        return Ok(entries);
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let numbered = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().parse::<u32>().is_ok());
+        if path.is_dir() && numbered {
+            entries.push(path);
+        }
+    }
+    Ok(entries)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+/// Advisory lock on a data set, released when dropped.
+///
+/// The lock is taken on a separate lock file rather than the data file itself,
+/// because `save_snapshot` replaces the data file whenever it writes.
+pub struct FileLock {
+    file: File,
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+pub fn lock_file(
+    lock_path: &str,
+    mode: LockMode,
+) -> Result<FileLock, Box<dyn Error + Send + Sync>> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path)?;
+    match mode {
+        LockMode::Shared => file.lock_shared()?,
+        LockMode::Exclusive => file.lock()?,
+    }
+    Ok(FileLock { file })
+}
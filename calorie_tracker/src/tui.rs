@@ -0,0 +1,853 @@
+//! Full-screen dashboard with the totals, foods and workouts of a day and a
+//! chart of the week leading up to it.
+
+use calorie_tracker::api::NutritionInfo;
+use calorie_tracker::day::Day;
+use calorie_tracker::food::parse_time;
+use calorie_tracker::{App, AppResult, Food, Workout, WorkoutType};
+use chrono::{Duration, Local, NaiveDate};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{
+    Bar, BarChart, BarGroup, Block, Cell, Gauge, Paragraph, Row, Table, TableState,
+};
+use ratatui::{DefaultTerminal, Frame};
+
+const HELP: &str =
+    "←/→ day  t today  tab switch  ↑/↓ select  a add  e edit  d delete  u undo  r redo  q quit";
+const REVIEW_HELP: &str = "↑/↓ select  space accept/reject  enter log accepted  esc cancel";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Foods,
+    Workouts,
+}
+
+/// What the text typed at the bottom line is for.
+#[derive(Clone, Copy)]
+enum Prompt {
+    AddFood,
+    AddWorkout,
+    EditFood(FoodField),
+    EditWorkout(WorkoutField),
+}
+
+impl Prompt {
+    fn label(self) -> &'static str {
+        match self {
+            Prompt::AddFood => "Add food (e.g. 2 eggs and a cup of rice)",
+            Prompt::AddWorkout => "Add workout (e.g. cardio 30 250, weights 45, ran 5k)",
+            Prompt::EditFood(field) => field.label(),
+            Prompt::EditWorkout(field) => field.label(),
+        }
+    }
+}
+
+/// An entry being edited one field at a time, with its position in the day.
+enum Draft {
+    Food(usize, Food),
+    Workout(usize, Workout),
+}
+
+/// The fields of a food, in the order they are edited.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FoodField {
+    Name,
+    Quantity,
+    Unit,
+    Calories,
+    Protein,
+    Fat,
+    Carbs,
+    Meal,
+    Time,
+}
+
+impl FoodField {
+    const ALL: [FoodField; 9] = [
+        FoodField::Name,
+        FoodField::Quantity,
+        FoodField::Unit,
+        FoodField::Calories,
+        FoodField::Protein,
+        FoodField::Fat,
+        FoodField::Carbs,
+        FoodField::Meal,
+        FoodField::Time,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            FoodField::Name => "Food name",
+            FoodField::Quantity => "Quantity (scales the nutrients)",
+            FoodField::Unit => "Unit",
+            FoodField::Calories => "Calories",
+            FoodField::Protein => "Protein (g)",
+            FoodField::Fat => "Fat (g)",
+            FoodField::Carbs => "Carbohydrates (g)",
+            FoodField::Meal => "Meal (breakfast, lunch, dinner, snack or empty)",
+            FoodField::Time => "Time eaten (HH:MM or empty)",
+        }
+    }
+
+    fn next(self) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .skip_while(|&&field| field != self)
+            .nth(1)
+            .copied()
+    }
+
+    /// The value of the field in `food`, as it would be typed.
+    fn value(self, food: &Food) -> String {
+        let round = |value: f64| ((value * 10.0).round() / 10.0).to_string();
+        match self {
+            FoodField::Name => food.name.clone(),
+            FoodField::Quantity => food.quantity.to_string(),
+            FoodField::Unit => food.unit.clone(),
+            FoodField::Calories => round(food.calories),
+            FoodField::Protein => round(food.protein),
+            FoodField::Fat => round(food.fat),
+            FoodField::Carbs => round(food.carbs),
+            FoodField::Meal => food.meal.map_or(String::new(), |meal| meal.to_string()),
+            FoodField::Time => food
+                .time
+                .map_or(String::new(), |time| time.format("%H:%M").to_string()),
+        }
+    }
+
+    fn set(self, food: &mut Food, text: &str) -> Result<(), String> {
+        match self {
+            FoodField::Name => food.name = parse_required(text, "name")?,
+            FoodField::Quantity => {
+                let quantity = parse_amount(text)?;
+                if quantity == 0.0 {
+                    return Err("The quantity must be more than 0".to_string());
+                }
+                *food = food.with_quantity(quantity);
+            }
+            FoodField::Unit => food.unit = parse_required(text, "unit")?,
+            FoodField::Calories => food.calories = parse_amount(text)?,
+            FoodField::Protein => food.protein = parse_amount(text)?,
+            FoodField::Fat => food.fat = parse_amount(text)?,
+            FoodField::Carbs => food.carbs = parse_amount(text)?,
+            FoodField::Meal => {
+                food.meal = match text {
+                    "" | "none" => None,
+                    meal => Some(meal.parse()?),
+                }
+            }
+            FoodField::Time => {
+                food.time = match text {
+                    "" | "none" => None,
+                    time => Some(parse_time(time)?),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The fields of a workout, in the order they are edited.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum WorkoutField {
+    Name,
+    Type,
+    Duration,
+    Calories,
+}
+
+impl WorkoutField {
+    const ALL: [WorkoutField; 4] = [
+        WorkoutField::Name,
+        WorkoutField::Type,
+        WorkoutField::Duration,
+        WorkoutField::Calories,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            WorkoutField::Name => "Workout name (empty to name it by its type)",
+            WorkoutField::Type => "Type (cardio or weights)",
+            WorkoutField::Duration => "Duration (minutes)",
+            WorkoutField::Calories => "Calories burnt",
+        }
+    }
+
+    fn next(self) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .skip_while(|&&field| field != self)
+            .nth(1)
+            .copied()
+    }
+
+    /// The value of the field in `workout`, as it would be typed.
+    fn value(self, workout: &Workout) -> String {
+        match self {
+            WorkoutField::Name => workout.name.clone().unwrap_or_default(),
+            WorkoutField::Type => match workout.workout_type {
+                WorkoutType::Cardio => "cardio".to_string(),
+                WorkoutType::WeightLifting => "weights".to_string(),
+            },
+            WorkoutField::Duration => workout.duration.to_string(),
+            WorkoutField::Calories => workout.calories_burnt.to_string(),
+        }
+    }
+
+    fn set(self, workout: &mut Workout, text: &str) -> Result<(), String> {
+        let whole = |text: &str| {
+            text.parse::<u32>()
+                .map_err(|_| format!("Invalid whole number '{}'", text))
+        };
+        match self {
+            WorkoutField::Name => workout.name = Some(text.to_string()).filter(|n| !n.is_empty()),
+            WorkoutField::Type => {
+                workout.workout_type = parse_workout_type(text)
+                    .ok_or_else(|| format!("Unknown workout type '{}'", text))?
+            }
+            WorkoutField::Duration => {
+                workout.duration = whole(text)?;
+                if workout.duration == 0 {
+                    return Err("The duration must be at least a minute".to_string());
+                }
+            }
+            WorkoutField::Calories => workout.calories_burnt = whole(text)?,
+        }
+        Ok(())
+    }
+}
+
+fn parse_required(text: &str, what: &str) -> Result<String, String> {
+    if text.is_empty() {
+        Err(format!("The {} cannot be empty", what))
+    } else {
+        Ok(text.to_string())
+    }
+}
+
+fn parse_amount(text: &str) -> Result<f64, String> {
+    text.parse()
+        .ok()
+        .filter(|amount: &f64| amount.is_finite() && *amount >= 0.0)
+        .ok_or_else(|| format!("Invalid amount '{}'", text))
+}
+
+/// Foods found for the add food prompt, shown to be accepted or rejected
+/// before anything is logged.
+struct Review {
+    items: Vec<NutritionInfo>,
+    accepted: Vec<bool>,
+    table: TableState,
+}
+
+struct Dashboard {
+    date: NaiveDate,
+    focus: Focus,
+    foods: TableState,
+    workouts: TableState,
+    input: Option<(Prompt, String)>,
+    /// Input submitted for a provider request, run after "Searching…" is drawn.
+    pending: Option<(Prompt, String)>,
+    review: Option<Review>,
+    draft: Option<Draft>,
+    status: Option<(String, bool)>, // message, and whether it is an error
+}
+
+/// Runs the dashboard until the user quits, starting on the current day.
+pub fn run(app: &mut App) -> AppResult<()> {
+    let date = app.get_current_day()?.date;
+    let mut terminal = ratatui::init();
+    let result = Dashboard::new(date).run(app, &mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl Dashboard {
+    fn new(date: NaiveDate) -> Self {
+        Self {
+            date,
+            focus: Focus::Foods,
+            foods: TableState::default().with_selected(0),
+            workouts: TableState::default().with_selected(0),
+            input: None,
+            pending: None,
+            review: None,
+            draft: None,
+            status: None,
+        }
+    }
+
+    fn run(&mut self, app: &mut App, terminal: &mut DefaultTerminal) -> AppResult<()> {
+        app.load_range(self.date - Duration::days(6), self.date)?;
+        loop {
+            terminal.draw(|frame| self.render(frame, app))?;
+            if let Some((prompt, text)) = self.pending.take() {
+                let result = self.submit(app, prompt, &text);
+                self.report(result);
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(app, key)? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn day<'a>(&self, app: &'a App) -> Option<&'a Day> {
+        app.days_in_range(self.date, self.date).next()
+    }
+
+    fn report(&mut self, result: AppResult<String>) {
+        self.status = Some(match result {
+            Ok(message) => (message, false),
+            Err(e) => (e.to_string(), true),
+        });
+    }
+
+    /// Handles a key press; returns false when the user quits.
+    fn handle_key(&mut self, app: &mut App, key: KeyEvent) -> AppResult<bool> {
+        if let Some((prompt, text)) = &mut self.input {
+            match key.code {
+                KeyCode::Enter => {
+                    let (prompt, text) = (*prompt, text.trim().to_string());
+                    self.input = None;
+                    if matches!(prompt, Prompt::EditFood(_) | Prompt::EditWorkout(_)) {
+                        self.edit_field(app, prompt, &text);
+                    } else if !text.is_empty() {
+                        self.status = Some(("Searching…".to_string(), false));
+                        self.pending = Some((prompt, text));
+                    }
+                }
+                KeyCode::Esc => {
+                    self.input = None;
+                    if self.draft.take().is_some() {
+                        self.status = Some(("Nothing was changed".to_string(), false));
+                    }
+                }
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(c) => text.push(c),
+                _ => {}
+            }
+            return Ok(true);
+        }
+        if self.review.is_some() {
+            self.handle_review_key(app, key);
+            return Ok(true);
+        }
+
+        self.status = None;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Left | KeyCode::Char('h') => self.go_to(app, self.date - Duration::days(1))?,
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.go_to(app, self.date + Duration::days(1))?
+            }
+            KeyCode::Char('t') => self.go_to(app, Local::now().date_naive())?,
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Foods => Focus::Workouts,
+                    Focus::Workouts => Focus::Foods,
+                }
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.table().select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table().select_previous(),
+            KeyCode::Char('a') => {
+                let prompt = match self.focus {
+                    Focus::Foods => Prompt::AddFood,
+                    Focus::Workouts => Prompt::AddWorkout,
+                };
+                self.input = Some((prompt, String::new()));
+            }
+            KeyCode::Char('e') | KeyCode::Enter => self.edit(app),
+            KeyCode::Char('d') | KeyCode::Delete => {
+                let result = self.remove(app);
+                self.report(result);
+            }
+            KeyCode::Char('u') => {
+                let result = app.undo().map(|undone| match undone {
+                    Some(description) => format!("Undid: {}", description),
+                    None => "Nothing to undo".to_string(),
+                });
+                self.report(result);
+            }
+            KeyCode::Char('r') => {
+                let result = app.redo().map(|redone| match redone {
+                    Some(description) => format!("Redid: {}", description),
+                    None => "Nothing to redo".to_string(),
+                });
+                self.report(result);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn handle_review_key(&mut self, app: &mut App, key: KeyEvent) {
+        let Some(review) = &mut self.review else {
+            return;
+        };
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => review.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => review.table.select_previous(),
+            KeyCode::Char(' ') => {
+                if let Some(accepted) = review
+                    .table
+                    .selected()
+                    .and_then(|index| review.accepted.get_mut(index))
+                {
+                    *accepted = !*accepted;
+                }
+            }
+            KeyCode::Enter => {
+                let Some(review) = self.review.take() else {
+                    return;
+                };
+                let accepted: Vec<NutritionInfo> = review
+                    .items
+                    .into_iter()
+                    .zip(review.accepted)
+                    .filter_map(|(item, accepted)| accepted.then_some(item))
+                    .collect();
+                let result = app
+                    .change_day(self.date)
+                    .and_then(|_| app.add_nutrition(&accepted, None))
+                    .map(|added| {
+                        let names: Vec<&str> =
+                            added.iter().map(|food| food.name.as_str()).collect();
+                        if names.is_empty() {
+                            "Nothing was added".to_string()
+                        } else {
+                            format!("Added {}", names.join(", "))
+                        }
+                    });
+                self.report(result);
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.review = None;
+                self.status = Some(("Nothing was added".to_string(), false));
+            }
+            _ => {}
+        }
+    }
+
+    fn table(&mut self) -> &mut TableState {
+        match self.focus {
+            Focus::Foods => &mut self.foods,
+            Focus::Workouts => &mut self.workouts,
+        }
+    }
+
+    fn go_to(&mut self, app: &mut App, date: NaiveDate) -> AppResult<()> {
+        app.load_range(date - Duration::days(6), date)?;
+        self.date = date;
+        self.foods.select(Some(0));
+        self.workouts.select(Some(0));
+        Ok(())
+    }
+
+    fn selected(&self, app: &App) -> Option<usize> {
+        let day = self.day(app)?;
+        let (state, len) = match self.focus {
+            Focus::Foods => (&self.foods, day.foods.len()),
+            Focus::Workouts => (&self.workouts, day.workouts.len()),
+        };
+        state.selected().filter(|&index| index < len)
+    }
+
+    /// Starts editing the selected entry at its first field.
+    fn edit(&mut self, app: &App) {
+        let (Some(index), Some(day)) = (self.selected(app), self.day(app)) else {
+            return;
+        };
+        let (draft, input) = match self.focus {
+            Focus::Foods => {
+                let food = day.foods[index].clone();
+                let field = FoodField::Name;
+                let input = (Prompt::EditFood(field), field.value(&food));
+                (Draft::Food(index, food), input)
+            }
+            Focus::Workouts => {
+                let workout = day.workouts[index].clone();
+                let field = WorkoutField::Name;
+                let input = (Prompt::EditWorkout(field), field.value(&workout));
+                (Draft::Workout(index, workout), input)
+            }
+        };
+        self.draft = Some(draft);
+        self.input = Some(input);
+    }
+
+    /// Takes `text` for the field being edited and asks for the next one, or
+    /// saves the entry after the last field. An invalid value is asked for
+    /// again.
+    fn edit_field(&mut self, app: &mut App, prompt: Prompt, text: &str) {
+        // A value left as shown keeps its unrounded amount.
+        let next = match (&mut self.draft, prompt) {
+            (Some(Draft::Food(_, food)), Prompt::EditFood(field)) => {
+                let set = if text == field.value(food) {
+                    Ok(())
+                } else {
+                    field.set(food, text)
+                };
+                set.map(|()| {
+                    field
+                        .next()
+                        .map(|next| (Prompt::EditFood(next), next.value(food)))
+                })
+            }
+            (Some(Draft::Workout(_, workout)), Prompt::EditWorkout(field)) => {
+                let set = if text == field.value(workout) {
+                    Ok(())
+                } else {
+                    field.set(workout, text)
+                };
+                set.map(|()| {
+                    field
+                        .next()
+                        .map(|next| (Prompt::EditWorkout(next), next.value(workout)))
+                })
+            }
+            _ => return,
+        };
+        match next {
+            Ok(Some(input)) => {
+                self.status = None;
+                self.input = Some(input);
+            }
+            Ok(None) => {
+                let result = self.save_draft(app);
+                self.report(result);
+            }
+            Err(message) => {
+                self.status = Some((message, true));
+                self.input = Some((prompt, text.to_string()));
+            }
+        }
+    }
+
+    fn save_draft(&mut self, app: &mut App) -> AppResult<String> {
+        match self.draft.take() {
+            Some(Draft::Food(index, food)) => {
+                let name = food.name.clone();
+                app.edit_food(self.date, index, food)?;
+                Ok(format!("Saved {}", name))
+            }
+            Some(Draft::Workout(index, workout)) => {
+                let label = workout.label();
+                app.edit_workout(self.date, index, workout)?;
+                Ok(format!("Saved {}", label))
+            }
+            None => Ok("Nothing was changed".to_string()),
+        }
+    }
+
+    fn remove(&mut self, app: &mut App) -> AppResult<String> {
+        let Some(index) = self.selected(app) else {
+            return Ok("Nothing selected".to_string());
+        };
+        app.change_day(self.date)?;
+        match self.focus {
+            Focus::Foods => app.remove_food(index)?,
+            Focus::Workouts => app.remove_workout(index)?,
+        }
+        Ok("Removed".to_string())
+    }
+
+    fn submit(&mut self, app: &mut App, prompt: Prompt, text: &str) -> AppResult<String> {
+        app.change_day(self.date)?;
+        match prompt {
+            Prompt::AddFood => {
+                let items = app.find_foods(text)?;
+                if items.is_empty() {
+                    return Err(format!("No foods recognized in '{}'", text).into());
+                }
+                let count = items.len();
+                self.review = Some(Review {
+                    accepted: vec![true; count],
+                    items,
+                    table: TableState::default().with_selected(0),
+                });
+                Ok(format!("Found {} foods", count))
+            }
+            Prompt::AddWorkout => {
+                let workouts = match parse_workout(text) {
+                    Some(workout) => vec![workout?],
+                    None => app.search_exercise(text)?,
+                };
+                if workouts.is_empty() {
+                    return Err(format!("No exercise recognized in '{}'", text).into());
+                }
+                let mut labels = Vec::new();
+                for workout in workouts {
+                    labels.push(workout.label());
+                    app.add_workout(workout)?;
+                }
+                Ok(format!("Added {}", labels.join(", ")))
+            }
+            Prompt::EditFood(_) | Prompt::EditWorkout(_) => {
+                unreachable!("edits are taken field by field")
+            }
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, app: &App) {
+        let [header, gauges, lists, chart, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Min(6),
+            Constraint::Length(9),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let today = Local::now().date_naive();
+        let title = format!(
+            " 🍏 Calorie Tracker  {}{}",
+            self.date.format("%A %Y-%m-%d"),
+            if self.date == today { " (today)" } else { "" }
+        );
+        frame.render_widget(Paragraph::new(title).bold().fg(Color::Green), header);
+
+        self.render_gauges(frame, app, gauges);
+        let [foods, workouts] =
+            Layout::horizontal([Constraint::Percentage(62), Constraint::Percentage(38)])
+                .areas(lists);
+        match &mut self.review {
+            Some(review) => render_review(frame, review, foods),
+            None => self.render_foods(frame, app, foods),
+        }
+        self.render_workouts(frame, app, workouts);
+        self.render_week(frame, app, chart);
+        self.render_footer(frame, footer);
+    }
+
+    fn render_gauges(&self, frame: &mut Frame, app: &App, area: Rect) {
+        let day = self.day(app);
+        let eaten = day.map_or(0.0, Day::total_calories);
+        let protein = day.map_or(0.0, Day::total_protein);
+        let burnt = day.map_or(0, Day::calories_burnt);
+        // Eating the basal metabolic rate plus what was burnt keeps the
+        // weight; the protein goal follows the workouts of the past week.
+        let calorie_goal = app.calculate_bmr() as f64 + burnt as f64;
+        let workouts = app
+            .days_in_range(self.date - Duration::days(6), self.date)
+            .map(|day| day.workouts.len() as u32)
+            .sum();
+        let protein_goal = app.calculate_recommended_protein(workouts) as f64;
+
+        let [calories_area, protein_area, burnt_area] = Layout::horizontal([
+            Constraint::Percentage(40),
+            Constraint::Percentage(40),
+            Constraint::Percentage(20),
+        ])
+        .areas(area);
+        frame.render_widget(
+            goal_gauge("Calories", eaten, calorie_goal, "kcal", Color::Yellow),
+            calories_area,
+        );
+        frame.render_widget(
+            goal_gauge("Protein", protein, protein_goal, "g", Color::Cyan),
+            protein_area,
+        );
+        frame.render_widget(
+            Paragraph::new(format!("{} kcal", burnt))
+                .fg(Color::Red)
+                .centered()
+                .block(Block::bordered().title("Burnt")),
+            burnt_area,
+        );
+    }
+
+    fn render_foods(&mut self, frame: &mut Frame, app: &App, area: Rect) {
+        let foods = self.day(app).map_or(&[][..], |day| &day.foods);
+        let rows = foods.iter().map(|food| {
+            Row::new(vec![
+                Cell::from(food.name.clone()),
+                Cell::from(format!("{} {}", food.quantity, food.unit)),
+                Cell::from(format!("{:.0}", food.calories)),
+                Cell::from(format!("{:.1}", food.protein)),
+                Cell::from(food.occasion().unwrap_or_default()),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(12),
+                Constraint::Length(6),
+                Constraint::Length(8),
+                Constraint::Length(18),
+            ],
+        )
+        .header(Row::new(["Food", "Amount", "kcal", "Protein", "When"]).bold())
+        .block(self.list_block("Foods", Focus::Foods))
+        .row_highlight_style(self.highlight(Focus::Foods));
+        frame.render_stateful_widget(table, area, &mut self.foods);
+    }
+
+    fn render_workouts(&mut self, frame: &mut Frame, app: &App, area: Rect) {
+        let workouts = self.day(app).map_or(&[][..], |day| &day.workouts);
+        let rows = workouts.iter().map(|workout| {
+            Row::new(vec![
+                workout.label(),
+                format!("{} min", workout.duration),
+                workout.calories_burnt.to_string(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(8),
+                Constraint::Length(6),
+            ],
+        )
+        .header(Row::new(["Workout", "Time", "kcal"]).bold())
+        .block(self.list_block("Workouts", Focus::Workouts))
+        .row_highlight_style(self.highlight(Focus::Workouts));
+        frame.render_stateful_widget(table, area, &mut self.workouts);
+    }
+
+    fn list_block(&self, title: &'static str, focus: Focus) -> Block<'static> {
+        let block = Block::bordered().title(title);
+        if self.focus == focus {
+            block.border_style(Style::new().fg(Color::Cyan))
+        } else {
+            block
+        }
+    }
+
+    fn highlight(&self, focus: Focus) -> Style {
+        if self.focus == focus {
+            Style::new().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::new()
+        }
+    }
+
+    fn render_week(&self, frame: &mut Frame, app: &App, area: Rect) {
+        let start = self.date - Duration::days(6);
+        let days: Vec<&Day> = app.days_in_range(start, self.date).collect();
+        let bars: Vec<Bar> = (0..7)
+            .map(|i| {
+                let date = start + Duration::days(i);
+                let calories = days
+                    .iter()
+                    .find(|day| day.date == date)
+                    .map_or(0.0, |day| day.total_calories());
+                let color = if date == self.date {
+                    Color::Yellow
+                } else {
+                    Color::DarkGray
+                };
+                Bar::default()
+                    .value(calories.round() as u64)
+                    .label(Line::from(date.format("%a %d").to_string()))
+                    .style(Style::new().fg(color))
+            })
+            .collect();
+        let chart = BarChart::default()
+            .block(Block::bordered().title("Calories in the last 7 days"))
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(9)
+            .bar_gap(2);
+        frame.render_widget(chart, area);
+    }
+
+    fn render_footer(&self, frame: &mut Frame, area: Rect) {
+        let footer = match (&self.input, &self.status) {
+            (Some((prompt, text)), Some((message, true))) => {
+                Paragraph::new(format!("{} ({}): {}█", prompt.label(), message, text))
+                    .fg(Color::Red)
+            }
+            (Some((prompt, text)), _) => {
+                Paragraph::new(format!("{}: {}█", prompt.label(), text)).fg(Color::Cyan)
+            }
+            (None, _) if self.review.is_some() => Paragraph::new(REVIEW_HELP).fg(Color::Cyan),
+            (None, Some((message, true))) => Paragraph::new(message.as_str()).fg(Color::Red),
+            (None, Some((message, false))) => Paragraph::new(message.as_str()).fg(Color::Green),
+            (None, None) => Paragraph::new(HELP).fg(Color::DarkGray),
+        };
+        frame.render_widget(footer, area);
+    }
+}
+
+fn render_review(frame: &mut Frame, review: &mut Review, area: Rect) {
+    let rows = review
+        .items
+        .iter()
+        .zip(&review.accepted)
+        .map(|(item, &accepted)| {
+            let row = Row::new(vec![
+                Cell::from(if accepted { "✓" } else { "✗" }),
+                Cell::from(item.name.clone()),
+                Cell::from(format!("{} {}", item.quantity, item.unit)),
+                Cell::from(format!("{:.0}", item.calories)),
+                Cell::from(format!("{:.1}", item.protein)),
+            ]);
+            if accepted {
+                row
+            } else {
+                row.fg(Color::DarkGray)
+            }
+        });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(12),
+            Constraint::Length(6),
+            Constraint::Length(8),
+        ],
+    )
+    .header(Row::new(["", "Found", "Amount", "kcal", "Protein"]).bold())
+    .block(
+        Block::bordered()
+            .title("Log these foods?")
+            .border_style(Style::new().fg(Color::Cyan)),
+    )
+    .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, area, &mut review.table);
+}
+
+fn goal_gauge(title: &str, value: f64, goal: f64, unit: &str, color: Color) -> Gauge<'static> {
+    let ratio = if goal > 0.0 { value / goal } else { 0.0 };
+    Gauge::default()
+        .block(Block::bordered().title(title.to_string()))
+        .gauge_style(Style::new().fg(if ratio > 1.0 { Color::Red } else { color }))
+        .ratio(ratio.clamp(0.0, 1.0))
+        .label(format!("{:.0} / {:.0} {}", value, goal, unit))
+}
+
+/// A workout typed as "cardio <minutes> <calories>" or "weights <minutes>";
+/// `None` for anything else, which is left to the providers.
+fn parse_workout(text: &str) -> Option<AppResult<Workout>> {
+    let mut words = text.split_whitespace();
+    let workout_type = parse_workout_type(words.next()?)?;
+    let minutes = words.next()?.parse().ok()?;
+    let mut workout = Workout::new(workout_type, minutes);
+    match (workout_type, words.next().map(str::parse::<u32>)) {
+        (_, Some(Ok(calories))) => workout.calories_burnt = calories,
+        (_, Some(Err(_))) => return Some(Err("Calories must be a whole number".into())),
+        (WorkoutType::Cardio, None) => {
+            return Some(Err("Give the calories burnt, e.g. cardio 30 250".into()))
+        }
+        (WorkoutType::WeightLifting, None) => {}
+    }
+    Some(Ok(workout))
+}
+
+fn parse_workout_type(word: &str) -> Option<WorkoutType> {
+    match word.to_lowercase().as_str() {
+        "cardio" => Some(WorkoutType::Cardio),
+        "weights" | "lifting" | "weight-lifting" | "strength" => Some(WorkoutType::WeightLifting),
+        _ => None,
+    }
+}
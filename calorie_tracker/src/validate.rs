@@ -0,0 +1,151 @@
+use crate::day::Day;
+use chrono::NaiveDate;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    DuplicateDate(NaiveDate),
+    Unsorted {
+        date: NaiveDate,
+        after: NaiveDate,
+    },
+    EmptyName {
+        date: NaiveDate,
+        index: usize,
+    },
+    InvalidValue {
+        date: NaiveDate,
+        index: usize,
+        name: String,
+        field: &'static str,
+        value: f64,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::DuplicateDate(date) => write!(f, "{} is recorded more than once", date),
+            Problem::Unsorted { date, after } => {
+                write!(f, "{} is stored after the later date {}", date, after)
+            }
+            Problem::EmptyName { date, index } => {
+                write!(f, "{}: entry #{} has no name", date, index + 1)
+            }
+            Problem::InvalidValue {
+                date,
+                index,
+                name,
+                field,
+                value,
+            } => write!(
+                f,
+                "{}: entry #{} ({}) has invalid {} {}",
+                date,
+                index + 1,
+                name,
+                field,
+                value
+            ),
+        }
+    }
+}
+
+/// Problems found by `validate`, in the order the days are stored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub problems: Vec<Problem>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+pub fn validate(days: &[Day]) -> Report {
+    let mut problems = Vec::new();
+    let mut seen: Vec<NaiveDate> = Vec::new();
+    let mut latest: Option<NaiveDate> = None;
+
+    for day in days {
+        if seen.contains(&day.date) {
+            if !problems.contains(&Problem::DuplicateDate(day.date)) {
+                problems.push(Problem::DuplicateDate(day.date));
+            }
+        } else {
+            seen.push(day.date);
+        }
+        match latest {
+            Some(after) if day.date < after => problems.push(Problem::Unsorted {
+                date: day.date,
+                after,
+            }),
+            _ => latest = Some(day.date),
+        }
+        problems.extend(validate_day(day));
+    }
+
+    Report { problems }
+}
+
+/// Checks the entries of a single day.
+pub fn validate_day(day: &Day) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (index, food) in day.foods.iter().enumerate() {
+        if food.name.trim().is_empty() {
+            problems.push(Problem::EmptyName {
+                date: day.date,
+                index,
+            });
+        }
+        let fields = [
+            ("quantity", food.quantity),
+            ("protein", food.protein),
+            ("fat", food.fat),
+            ("carbs", food.carbs),
+            ("calories", food.calories),
+        ];
+        for (field, value) in fields {
+            if !value.is_finite() || value < 0.0 {
+                problems.push(Problem::InvalidValue {
+                    date: day.date,
+                    index,
+                    name: food.name.clone(),
+                    field,
+                    value,
+                });
+            }
+        }
+    }
+    problems
+}
+
+/// Returns the days with every problem `validate` reports fixed: entries with
+/// invalid values are dropped, duplicate days are merged and days are sorted.
+pub fn repair(days: Vec<Day>) -> Vec<Day> {
+    let mut repaired: Vec<Day> = Vec::new();
+    for mut day in days {
+        let invalid: Vec<usize> = validate_day(&day)
+            .iter()
+            .filter_map(|problem| match problem {
+                Problem::EmptyName { index, .. } | Problem::InvalidValue { index, .. } => {
+                    Some(*index)
+                }
+                _ => None,
+            })
+            .collect();
+        let mut index = 0;
+        day.foods.retain(|_| {
+            index += 1;
+            !invalid.contains(&(index - 1))
+        });
+
+        match repaired.iter_mut().find(|d| d.date == day.date) {
+            Some(existing) => existing.merge(day),
+            None => repaired.push(day),
+        }
+    }
+    repaired.sort_by_key(|day| day.date);
+    repaired
+}
@@ -0,0 +1,217 @@
+#[allow(dead_code)]
+mod common;
+
+use calorie_tracker::api::{
+    search_and_get_nutrition, HttpClient, NutritionCache, NutritionInfo, NutritionProvider,
+    Nutritionix,
+};
+use calorie_tracker::config::{Credentials, HttpSettings};
+use chrono::Duration;
+use common::{MockServer, Reply};
+use std::path::PathBuf;
+
+fn providers(server: &MockServer) -> Vec<Box<dyn NutritionProvider>> {
+    let client = HttpClient::new(&HttpSettings {
+        retries: 0,
+        ..HttpSettings::default()
+    })
+    .unwrap();
+    let credentials = Credentials {
+        app_id: "test-id".to_string(),
+        app_key: "test-key".to_string(),
+    };
+    vec![Box::new(
+        Nutritionix::new(credentials, client).with_base_url(server.url()),
+    )]
+}
+
+fn results() -> Reply {
+    Reply::fixture(200, "nutritionix/natural_nutrients.json")
+}
+
+fn rice() -> NutritionInfo {
+    NutritionInfo {
+        name: "rice".to_string(),
+        quantity: 1.0,
+        unit: "cup".to_string(),
+        calories: 205.4,
+        protein: 4.25,
+        fat: 0.44,
+        carbs: 44.5,
+    }
+}
+
+/// A cache file of its own, removed afterwards.
+struct CacheFile(PathBuf);
+
+impl CacheFile {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "calorie_tracker-cache-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        Self(path)
+    }
+
+    fn open(&self, max_age: Duration) -> NutritionCache {
+        NutritionCache::load(&self.0.to_string_lossy(), None, max_age)
+    }
+}
+
+impl Drop for CacheFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[tokio::test]
+async fn answers_repeated_queries_from_the_cache() {
+    let server = MockServer::start(vec![results()]);
+    let providers = providers(&server);
+    let cache = NutritionCache::in_memory(Duration::days(1));
+
+    for query in ["2 eggs and a cup of rice", "2 Eggs  and a cup of rice"] {
+        let foods = search_and_get_nutrition(query, &providers, Some(&cache))
+            .await
+            .unwrap();
+        assert_eq!(foods.len(), 2);
+    }
+    assert_eq!(server.requests().len(), 1);
+    let stats = cache.stats();
+    assert_eq!((stats.queries, stats.hits, stats.misses), (1, 1, 1));
+}
+
+#[tokio::test]
+async fn answers_other_quantities_of_cached_foods() {
+    let server = MockServer::start(vec![results()]);
+    let providers = providers(&server);
+    let cache = NutritionCache::in_memory(Duration::days(1));
+    search_and_get_nutrition("2 eggs and a cup of rice", &providers, Some(&cache))
+        .await
+        .unwrap();
+
+    let foods = search_and_get_nutrition("3 eggs, 2 cups of rice", &providers, Some(&cache))
+        .await
+        .unwrap();
+    assert_eq!(server.requests().len(), 1);
+    assert_eq!(
+        foods,
+        vec![
+            NutritionInfo {
+                name: "egg".to_string(),
+                quantity: 3.0,
+                unit: "large".to_string(),
+                calories: 214.5,
+                protein: 18.84,
+                fat: 14.27,
+                carbs: 1.05,
+            },
+            NutritionInfo {
+                quantity: 2.0,
+                calories: 410.8,
+                protein: 8.5,
+                fat: 0.88,
+                carbs: 89.0,
+                ..rice()
+            },
+        ]
+    );
+    let stats = cache.stats();
+    assert_eq!((stats.queries, stats.foods, stats.hits), (1, 2, 1));
+}
+
+#[test]
+fn foods_in_other_units_are_not_answered_from_the_cache() {
+    let cache = NutritionCache::in_memory(Duration::days(1));
+    cache
+        .insert("2 eggs and a cup of rice", "Nutritionix", &[rice()])
+        .unwrap();
+    assert!(cache.get("100g rice").is_none());
+    // A plain count of rice does not say how much a piece would be.
+    assert!(cache.get("rice").is_none());
+    // Nor is a query with foods that are not cached.
+    assert!(cache.get("1 cup of rice and beans").is_none());
+    assert_eq!(cache.get("a cup of rice").unwrap().0, vec![rice()]);
+}
+
+#[tokio::test]
+async fn expired_results_are_fetched_again() {
+    let server = MockServer::start(vec![results(), results()]);
+    let providers = providers(&server);
+    let cache = NutritionCache::in_memory(Duration::zero());
+
+    for _ in 0..2 {
+        search_and_get_nutrition("2 eggs", &providers, Some(&cache))
+            .await
+            .unwrap();
+    }
+    assert_eq!(server.requests().len(), 2);
+    // The query and the two foods of its results.
+    assert_eq!(cache.stats().expired, 3);
+}
+
+#[tokio::test]
+async fn expired_results_are_used_when_providers_fail() {
+    let server = MockServer::start(vec![
+        results(),
+        Reply::fixture(429, "nutritionix/rate_limited.json"),
+    ]);
+    let providers = providers(&server);
+    let cache = NutritionCache::in_memory(Duration::zero());
+
+    let fetched = search_and_get_nutrition("2 eggs", &providers, Some(&cache))
+        .await
+        .unwrap();
+    let stale = search_and_get_nutrition("2 eggs", &providers, Some(&cache))
+        .await
+        .unwrap();
+    assert_eq!(stale, fetched);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn purges_expired_or_all_entries() {
+    let file = CacheFile::new("purge");
+    let mut cache = file.open(Duration::zero());
+    cache.insert("1 apple", "Nutritionix", &[rice()]).unwrap();
+    cache.insert("1 pear", "Nutritionix", &[rice()]).unwrap();
+    // Two queries and the food of their results.
+    assert_eq!(cache.stats().expired, 3);
+    assert!(cache.stats().size > 0);
+    assert_eq!(cache.purge(false).unwrap(), 3);
+
+    cache.set_max_age(Duration::days(1));
+    cache.insert("1 apple", "Nutritionix", &[rice()]).unwrap();
+    assert_eq!(cache.purge(false).unwrap(), 0);
+    assert_eq!(cache.purge(true).unwrap(), 2);
+    // The purge is saved.
+    assert_eq!(file.open(Duration::days(1)).stats().queries, 0);
+}
+
+#[test]
+fn keeps_results_across_restarts() {
+    let file = CacheFile::new("restart");
+    file.open(Duration::days(1))
+        .insert("1 cup of rice", "Nutritionix", &[rice()])
+        .unwrap();
+    let (items, provider) = file.open(Duration::days(1)).get("1 cup of rice").unwrap();
+    assert_eq!(items, vec![rice()]);
+    assert_eq!(provider, "Nutritionix");
+    let (items, _) = file.open(Duration::days(1)).get("2 cups of rice").unwrap();
+    assert_eq!(items[0].calories, 410.8);
+}
+
+#[test]
+fn corrupt_cache_files_are_treated_as_empty() {
+    let file = CacheFile::new("corrupt");
+    std::fs::write(&file.0, "{ not json").unwrap();
+    let cache = file.open(Duration::days(1));
+    assert_eq!(cache.stats().queries, 0);
+
+    cache
+        .insert("1 cup of rice", "Nutritionix", &[rice()])
+        .unwrap();
+    assert!(file.open(Duration::days(1)).get("1 cup of rice").is_some());
+}
@@ -0,0 +1,268 @@
+#[allow(dead_code)]
+mod common;
+
+use common::{MockServer, Reply};
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Runs the tracker on a data file of its own, without providers.
+struct Tracker {
+    dir: PathBuf,
+}
+
+impl Tracker {
+    fn new(name: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("calorie_tracker-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self { dir }
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        self.run_with_env(args, &[])
+    }
+
+    fn run_with_env(&self, args: &[&str], env: &[(&str, &str)]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_calorie_tracker"))
+            .args(args)
+            .env("CALORIE_TRACKER_DATA", self.dir.join("calories.json"))
+            .env("CALORIE_TRACKER_CONFIG", self.dir.join("config.json"))
+            .env_remove("NUTRITIONIX_APP_ID")
+            .env_remove("NUTRITIONIX_APP_KEY")
+            .env_remove("USDA_API_KEY")
+            .envs(env.iter().copied())
+            .output()
+            .unwrap()
+    }
+
+    /// Looks foods up with Nutritionix at `server`.
+    fn use_nutritionix(&self, server: &MockServer) {
+        let config = serde_json::json!({
+            "nutritionix": { "app_id": "test-id", "app_key": "test-key" },
+            "providers": ["nutritionix"],
+            "base_urls": { "nutritionix": server.url() },
+        });
+        std::fs::write(self.dir.join("config.json"), config.to_string()).unwrap();
+    }
+
+    fn json(&self, args: &[&str]) -> serde_json::Value {
+        let output = self.run(args);
+        assert!(output.status.success(), "{:?}", output);
+        serde_json::from_slice(&output.stdout).unwrap()
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn logs_and_removes_workouts() {
+    let tracker = Tracker::new("workouts");
+    let date = "2026-10-16";
+    let added = tracker.run(&[
+        "workout",
+        "add",
+        "cardio",
+        "--minutes",
+        "30",
+        "--calories",
+        "250",
+        "--name",
+        "running",
+        "--date",
+        date,
+    ]);
+    assert!(added.status.success(), "{:?}", added);
+    tracker.run(&[
+        "workout",
+        "add",
+        "weight-lifting",
+        "--minutes",
+        "60",
+        "--date",
+        date,
+    ]);
+
+    let day = tracker.json(&["day", "show", "--date", date, "--json"]);
+    assert_eq!(day["date"], date);
+    assert_eq!(day["workouts"][0]["name"], "running");
+    assert_eq!(day["workouts"][1]["calories_burnt"], 220);
+    assert_eq!(day["calories_burnt"], 470);
+
+    assert!(tracker
+        .run(&["workout", "remove", "1", "--date", date])
+        .status
+        .success());
+    let week = tracker.json(&["report", "week", "--date", date, "--json"]);
+    assert_eq!(week.as_array().unwrap().len(), 7);
+    assert_eq!(week[6]["date"], date);
+    assert_eq!(week[6]["calories_burnt"], 220);
+}
+
+#[test]
+fn showing_days_changes_nothing() {
+    let tracker = Tracker::new("read-only");
+    let added = tracker.run(&[
+        "workout",
+        "add",
+        "cardio",
+        "--minutes",
+        "30",
+        "--calories",
+        "250",
+        "--date",
+        "2026-10-16",
+    ]);
+    assert!(added.status.success(), "{:?}", added);
+    let journal = tracker.dir.join("calories.json.journal");
+    let before = std::fs::read(&journal).unwrap();
+
+    let day = tracker.json(&["day", "show", "--date", "2026-10-20", "--json"]);
+    assert_eq!(day["date"], "2026-10-20");
+    assert_eq!(day["foods"].as_array().unwrap().len(), 0);
+    let week = tracker.json(&["report", "week", "--date", "2026-10-18", "--json"]);
+    assert_eq!(week[4]["calories_burnt"], 250);
+    assert_eq!(std::fs::read(&journal).unwrap(), before);
+}
+
+#[test]
+fn updates_the_profile() {
+    let tracker = Tracker::new("profile");
+    assert!(tracker
+        .run(&["profile", "set", "--weight", "70", "--age", "30"])
+        .status
+        .success());
+    let profile = tracker.json(&["profile", "show", "--json"]);
+    assert_eq!(profile["weight"], 70.0);
+    assert_eq!(profile["age"], 30);
+    // Measurements not given are kept.
+    assert_eq!(profile["height"], 180.0);
+}
+
+#[test]
+fn exit_codes_tell_failures_apart() {
+    let tracker = Tracker::new("exit-codes");
+    let code = |args: &[&str]| tracker.run(args).status.code();
+
+    assert_eq!(
+        code(&["workout", "add", "cardio", "--minutes", "30"]),
+        Some(2)
+    );
+    assert_eq!(code(&["add", "2 eggs", "--meal", "brunch"]), Some(2));
+    assert_eq!(code(&["remove", "1"]), Some(3));
+    // No provider is configured and the library is empty.
+    assert_eq!(code(&["add", "2 eggs"]), Some(4));
+}
+
+#[test]
+fn adds_and_edits_foods() {
+    let tracker = Tracker::new("foods");
+    let server = MockServer::start(vec![Reply::fixture(
+        200,
+        "nutritionix/natural_nutrients.json",
+    )]);
+    tracker.use_nutritionix(&server);
+    let date = "2026-10-16";
+
+    let added = tracker.run(&[
+        "add",
+        "2 eggs and a cup of rice",
+        "--meal",
+        "breakfast",
+        "--date",
+        date,
+    ]);
+    assert!(added.status.success(), "{:?}", added);
+    let edited = tracker.run(&[
+        "edit",
+        "rice",
+        "--quantity",
+        "2",
+        "--time",
+        "08:30",
+        "--date",
+        date,
+    ]);
+    assert!(edited.status.success(), "{:?}", edited);
+
+    let day = tracker.json(&["day", "show", "--date", date, "--json"]);
+    let rice = &day["foods"][1];
+    assert_eq!(rice["quantity"], 2.0);
+    assert_eq!(rice["calories"], 410.8);
+    assert_eq!(rice["carbs"], 89.0);
+    assert_eq!(rice["meal"], "Breakfast");
+    assert_eq!(rice["time"], "08:30:00");
+    assert_eq!(day["total_calories"], 143.0 + 410.8);
+
+    let cleared = tracker.run(&[
+        "edit", "rice", "--meal", "none", "--time", "none", "--date", date,
+    ]);
+    assert!(cleared.status.success(), "{:?}", cleared);
+    let day = tracker.json(&["day", "show", "--date", date, "--json"]);
+    assert!(day["foods"][1].get("meal").is_none());
+    assert!(day["foods"][1].get("time").is_none());
+    assert_eq!(day["foods"][1]["calories"], 410.8);
+
+    let missing = tracker.run(&["edit", "toast", "--quantity", "1", "--date", date]);
+    assert_eq!(missing.status.code(), Some(3));
+}
+
+#[test]
+fn logging_a_food_again_adds_to_it() {
+    let tracker = Tracker::new("repeat");
+    // The second lookup is answered from the cache.
+    let server = MockServer::start(vec![Reply::fixture(
+        200,
+        "nutritionix/natural_nutrients.json",
+    )]);
+    tracker.use_nutritionix(&server);
+    let date = "2026-10-16";
+
+    for _ in 0..2 {
+        let added = tracker.run(&["add", "2 eggs and a cup of rice", "--date", date]);
+        assert!(added.status.success(), "{:?}", added);
+    }
+
+    let day = tracker.json(&["day", "show", "--date", date, "--json"]);
+    assert_eq!(day["foods"].as_array().unwrap().len(), 2);
+    assert_eq!(day["foods"][0]["quantity"], 4.0);
+    assert_eq!(day["foods"][0]["calories"], 286.0);
+    assert_eq!(day["foods"][1]["protein"], 8.5);
+    assert_eq!(day["total_calories"], 2.0 * (143.0 + 205.4));
+}
+
+#[test]
+fn opens_on_today() {
+    let tracker = Tracker::new("today");
+    let today = chrono::Local::now().date_naive().to_string();
+    tracker.run(&[
+        "workout",
+        "add",
+        "weight-lifting",
+        "--minutes",
+        "30",
+        "--date",
+        "2020-01-01",
+    ]);
+
+    let day = tracker.json(&["day", "show", "--json"]);
+    assert_eq!(day["date"], today);
+    assert_eq!(day["workouts"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn half_set_credentials_are_reported() {
+    let tracker = Tracker::new("credentials");
+    let env = [("NUTRITIONIX_APP_ID", "id")];
+    let output = tracker.run_with_env(&["day", "show"], &env);
+    assert!(output.status.success());
+
+    let output = tracker.run_with_env(&["add", "2 eggs"], &env);
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("NUTRITIONIX_APP_KEY is not"), "{}", stderr);
+}
@@ -0,0 +1,262 @@
+mod common;
+
+use calorie_tracker::api::{
+    search_and_get_nutrition, search_exercise, ApiError, HttpClient, NutritionInfo,
+    NutritionProvider, Nutritionix,
+};
+use calorie_tracker::config::{Credentials, HttpSettings};
+use calorie_tracker::{Gender, Profile, WorkoutType};
+use common::{MockServer, Reply};
+
+fn client(retries: u32) -> HttpClient {
+    HttpClient::new(&HttpSettings {
+        retries,
+        backoff_ms: 1,
+        ..HttpSettings::default()
+    })
+    .unwrap()
+}
+
+fn nutritionix(server: &MockServer, retries: u32) -> Nutritionix {
+    let credentials = Credentials {
+        app_id: "test-id".to_string(),
+        app_key: "test-key".to_string(),
+    };
+    Nutritionix::new(credentials, client(retries)).with_base_url(server.url())
+}
+
+#[tokio::test]
+async fn parses_natural_language_results() {
+    let server = MockServer::start(vec![Reply::fixture(
+        200,
+        "nutritionix/natural_nutrients.json",
+    )]);
+    let foods = nutritionix(&server, 0)
+        .search("2 eggs and a cup of rice")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        foods,
+        vec![
+            NutritionInfo {
+                name: "egg".to_string(),
+                quantity: 2.0,
+                unit: "large".to_string(),
+                calories: 143.0,
+                protein: 12.56,
+                fat: 9.51,
+                carbs: 0.7,
+            },
+            NutritionInfo {
+                name: "rice".to_string(),
+                quantity: 1.0,
+                unit: "cup".to_string(),
+                calories: 205.4,
+                protein: 4.25,
+                fat: 0.44,
+                carbs: 44.5,
+            },
+        ]
+    );
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].target, "/v2/natural/nutrients");
+    assert_eq!(requests[0].headers["x-app-id"], "test-id");
+    assert_eq!(requests[0].headers["x-app-key"], "test-key");
+    let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+    assert_eq!(body["query"], "2 eggs and a cup of rice");
+    assert!(body["timezone"].is_string());
+}
+
+#[tokio::test]
+async fn computes_missing_carbs_from_calories() {
+    let server = MockServer::start(vec![Reply::fixture(200, "nutritionix/missing_carbs.json")]);
+    let foods = nutritionix(&server, 0)
+        .search("200g chicken breast")
+        .await
+        .unwrap();
+
+    assert_eq!(foods.len(), 1);
+    // (330 - 4 * 62.04 - 9 * 7.14) / 4
+    assert_eq!(foods[0].carbs, 4.4);
+    assert_eq!(foods[0].protein, 62.04);
+}
+
+#[tokio::test]
+async fn unknown_food_gives_no_results() {
+    let server = MockServer::start(vec![Reply::fixture(404, "nutritionix/not_found.json")]);
+    let foods = nutritionix(&server, 0).search("zzz").await.unwrap();
+    assert!(foods.is_empty());
+}
+
+#[tokio::test]
+async fn rejected_credentials_are_auth_errors() {
+    let server = MockServer::start(vec![Reply::fixture(401, "nutritionix/unauthorized.json")]);
+    let error = nutritionix(&server, 3).search("1 apple").await.unwrap_err();
+    assert!(matches!(error, ApiError::Auth(_)), "{:?}", error);
+    // Authentication failures are not retried.
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn rate_limits_are_quota_errors() {
+    let server = MockServer::start(vec![Reply::fixture(429, "nutritionix/rate_limited.json")]);
+    let error = nutritionix(&server, 0).search("1 apple").await.unwrap_err();
+    assert!(matches!(error, ApiError::Quota(_)), "{:?}", error);
+}
+
+#[tokio::test]
+async fn rate_limits_are_retried_after_the_requested_delay() {
+    let server = MockServer::start(vec![
+        Reply::fixture(429, "nutritionix/rate_limited.json").header("Retry-After", "0"),
+        Reply::fixture(200, "nutritionix/natural_nutrients.json"),
+    ]);
+    let foods = nutritionix(&server, 1).search("2 eggs").await.unwrap();
+    assert_eq!(foods.len(), 2);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn rate_limits_asking_to_wait_past_the_timeout_are_not_retried() {
+    let server = MockServer::start(vec![
+        Reply::fixture(429, "nutritionix/rate_limited.json").header("Retry-After", "3600"),
+        Reply::fixture(200, "nutritionix/natural_nutrients.json"),
+    ]);
+    let started = std::time::Instant::now();
+    let error = nutritionix(&server, 3).search("2 eggs").await.unwrap_err();
+    assert!(matches!(error, ApiError::Quota(_)), "{:?}", error);
+    assert_eq!(server.requests().len(), 1);
+    assert!(started.elapsed().as_secs() < 5);
+}
+
+#[tokio::test]
+async fn malformed_json_is_a_parse_error() {
+    let server = MockServer::start(vec![Reply::fixture(200, "nutritionix/malformed.json")]);
+    let error = nutritionix(&server, 0).search("2 eggs").await.unwrap_err();
+    assert!(matches!(error, ApiError::Parse(_)), "{:?}", error);
+}
+
+#[tokio::test]
+async fn unreachable_server_is_a_network_error() {
+    // Nothing listens on port 1.
+    let api = Nutritionix::new(
+        Credentials {
+            app_id: "test-id".to_string(),
+            app_key: "test-key".to_string(),
+        },
+        client(0),
+    )
+    .with_base_url("http://127.0.0.1:1");
+    let error = api.search("2 eggs").await.unwrap_err();
+    assert!(matches!(error, ApiError::Network(_)), "{:?}", error);
+}
+
+#[tokio::test]
+async fn falls_back_to_the_next_provider() {
+    let failing = MockServer::start(vec![Reply::fixture(401, "nutritionix/unauthorized.json")]);
+    let working = MockServer::start(vec![Reply::fixture(
+        200,
+        "nutritionix/natural_nutrients.json",
+    )]);
+    let providers: Vec<Box<dyn NutritionProvider>> = vec![
+        Box::new(nutritionix(&failing, 0)),
+        Box::new(nutritionix(&working, 0)),
+    ];
+    let foods = search_and_get_nutrition("2 eggs", &providers, None)
+        .await
+        .unwrap();
+    assert_eq!(foods.len(), 2);
+}
+
+#[tokio::test]
+async fn reports_the_error_of_the_failing_provider() {
+    let server = MockServer::start(vec![Reply::fixture(429, "nutritionix/rate_limited.json")]);
+    let providers: Vec<Box<dyn NutritionProvider>> = vec![Box::new(nutritionix(&server, 0))];
+    let error = search_and_get_nutrition("2 eggs", &providers, None)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("Nutritionix"), "{}", error);
+    assert!(matches!(error, ApiError::Quota(_)), "{:?}", error);
+}
+
+#[tokio::test]
+async fn looks_up_barcodes_per_100_grams() {
+    let server = MockServer::start(vec![Reply::fixture(200, "nutritionix/search_item.json")]);
+    let product = nutritionix(&server, 0)
+        .lookup_barcode("5201054017906")
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(product.display_name(), "Greek Yogurt, Plain (Fage)");
+    assert_eq!(product.serving_grams, Some(170.0));
+    assert_eq!(product.calories, 88.2);
+    assert_eq!(product.protein, 8.8);
+    assert_eq!(
+        server.requests()[0].target,
+        "/v2/search/item?upc=5201054017906"
+    );
+}
+
+#[tokio::test]
+async fn unknown_barcodes_are_not_found() {
+    let server = MockServer::start(vec![Reply::fixture(404, "nutritionix/not_found.json")]);
+    let product = nutritionix(&server, 0)
+        .lookup_barcode("5201054017906")
+        .await
+        .unwrap();
+    assert!(product.is_none());
+}
+
+#[tokio::test]
+async fn maps_exercises_to_workouts() {
+    let server = MockServer::start(vec![Reply::fixture(
+        200,
+        "nutritionix/natural_exercise.json",
+    )]);
+    let providers: Vec<Box<dyn NutritionProvider>> = vec![Box::new(nutritionix(&server, 0))];
+    let profile = Profile {
+        height: 170.0,
+        weight: 65.5,
+        age: 31,
+        gender: Gender::Female,
+    };
+    let exercises = search_exercise(
+        "ran 30 min, 45 min yoga, 20 min lifting",
+        &profile,
+        &providers,
+    )
+    .await
+    .unwrap();
+    let workouts: Vec<_> = exercises.iter().map(|e| e.to_workout()).collect();
+
+    assert_eq!(workouts.len(), 3);
+    assert_eq!(workouts[0].name.as_deref(), Some("running"));
+    assert_eq!(workouts[0].workout_type, WorkoutType::Cardio);
+    assert_eq!(workouts[0].duration, 30);
+    assert_eq!(workouts[0].calories_burnt, 387);
+    assert_eq!(workouts[1].workout_type, WorkoutType::Cardio);
+    assert_eq!(workouts[2].workout_type, WorkoutType::WeightLifting);
+
+    let requests = server.requests();
+    assert_eq!(requests[0].target, "/v2/natural/exercise");
+    let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+    assert_eq!(body["weight_kg"], 65.5);
+    assert_eq!(body["height_cm"], 170.0);
+    assert_eq!(body["age"], 31);
+    assert_eq!(body["gender"], "female");
+}
+
+#[tokio::test]
+async fn suggests_common_food_names() {
+    let server = MockServer::start(vec![Reply::fixture(200, "nutritionix/search_instant.json")]);
+    let names = nutritionix(&server, 0).suggest("chick").await.unwrap();
+    assert_eq!(names, vec!["chicken breast", "chicken thigh"]);
+    assert_eq!(
+        server.requests()[0].target,
+        "/v2/search/instant?query=chick&branded=false"
+    );
+}
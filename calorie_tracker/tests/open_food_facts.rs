@@ -0,0 +1,95 @@
+#[allow(dead_code)]
+mod common;
+
+use calorie_tracker::api::open_food_facts::USER_AGENT;
+use calorie_tracker::api::{ApiError, HttpClient, NutritionInfo, NutritionProvider, OpenFoodFacts};
+use calorie_tracker::config::HttpSettings;
+use common::{MockServer, Reply};
+
+fn open_food_facts(server: &MockServer) -> OpenFoodFacts {
+    let client = HttpClient::new(&HttpSettings {
+        retries: 0,
+        ..HttpSettings::default()
+    })
+    .unwrap();
+    OpenFoodFacts::new(client).with_base_url(server.url())
+}
+
+#[tokio::test]
+async fn searches_the_first_product_with_nutrition_facts() {
+    let server = MockServer::start(vec![Reply::fixture(200, "open_food_facts/search.json")]);
+    let foods = open_food_facts(&server).search("oats").await.unwrap();
+
+    // Products without a name or energy value are skipped.
+    assert_eq!(
+        foods,
+        vec![NutritionInfo {
+            name: "Porridge oats".to_string(),
+            quantity: 100.0,
+            unit: "g".to_string(),
+            calories: 374.0,
+            protein: 11.0,
+            fat: 8.0,
+            // The product has no carbohydrate value.
+            carbs: 0.0,
+        }]
+    );
+    let requests = server.requests();
+    assert!(requests[0]
+        .target
+        .starts_with("/cgi/search.pl?search_terms=oats&"));
+    assert_eq!(requests[0].headers["user-agent"], USER_AGENT);
+}
+
+#[tokio::test]
+async fn failed_searches_are_errors() {
+    let server = MockServer::start(vec![Reply::new(404, "{}")]);
+    let error = open_food_facts(&server).search("oats").await.unwrap_err();
+    assert!(matches!(error, ApiError::NotFound(_)), "{:?}", error);
+}
+
+#[tokio::test]
+async fn looks_up_barcodes_per_100_grams() {
+    let server = MockServer::start(vec![Reply::fixture(200, "open_food_facts/product.json")]);
+    let product = open_food_facts(&server)
+        .lookup_barcode("5201054017906")
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(product.barcode, "5201054017906");
+    assert_eq!(product.display_name(), "Total 5% Greek yoghurt (Fage)");
+    assert_eq!(product.calories, 93.0);
+    assert_eq!(product.protein, 9.0);
+    assert_eq!(product.serving_grams, Some(170.0));
+    assert_eq!(product.per_serving().unwrap().calories, 158.1);
+    assert!(server.requests()[0]
+        .target
+        .starts_with("/api/v2/product/5201054017906.json?fields="));
+}
+
+#[tokio::test]
+async fn unknown_barcodes_are_not_found() {
+    let server = MockServer::start(vec![Reply::fixture(
+        404,
+        "open_food_facts/product_not_found.json",
+    )]);
+    let product = open_food_facts(&server)
+        .lookup_barcode("5201054017913")
+        .await
+        .unwrap();
+    assert!(product.is_none());
+}
+
+#[tokio::test]
+async fn products_without_status_1_are_not_found() {
+    let server = MockServer::start(vec![Reply::fixture(
+        200,
+        "open_food_facts/product_not_found.json",
+    )]);
+    let product = open_food_facts(&server)
+        .lookup_barcode("5201054017913")
+        .await
+        .unwrap();
+    assert!(product.is_none());
+}
@@ -0,0 +1,129 @@
+use calorie_tracker::library::{Library, LibraryFood};
+use calorie_tracker::parser::{parse_foods, parse_portion, split_items, Portion};
+use calorie_tracker::Food;
+use fuzzy_matcher::skim::SkimMatcherV2;
+
+fn library() -> Library {
+    let food = |id: &str, name: &str, protein, fat, carbs, calories| LibraryFood {
+        id: id.to_string(),
+        food: Food::new(name, 100.0, "g", protein, fat, carbs, calories),
+        barcode: None,
+        serving_size: None,
+        serving_grams: None,
+    };
+    let mut library = Library::default();
+    library.extend([
+        food("fdc:1", "Egg, whole, raw", 12.6, 9.5, 0.7, 143.0),
+        food("fdc:2", "Chicken breast, raw", 22.5, 2.6, 0.0, 120.0),
+        food("fdc:3", "Rice, white, cooked", 2.7, 0.3, 28.2, 130.0),
+        food("fdc:4", "Milk, whole", 3.2, 3.3, 4.8, 61.0),
+        food("fdc:5", "Beef, steak", 26.0, 15.0, 0.0, 242.0),
+    ]);
+    library
+}
+
+fn portion(quantity: f64, unit: Option<&str>, name: &str) -> Portion {
+    Portion {
+        quantity,
+        unit: unit.map(str::to_string),
+        name: name.to_string(),
+    }
+}
+
+#[test]
+fn splits_descriptions_into_foods() {
+    assert_eq!(
+        split_items("2 eggs, 150g chicken breast and a cup of rice"),
+        vec!["2 eggs", "150g chicken breast", "a cup of rice"]
+    );
+    assert_eq!(split_items("toast + jam; tea"), vec!["toast", "jam", "tea"]);
+}
+
+#[test]
+fn parses_quantities_and_units() {
+    assert_eq!(parse_portion("2 eggs"), Some(portion(2.0, None, "eggs")));
+    assert_eq!(
+        parse_portion("150g Chicken Breast"),
+        Some(portion(150.0, Some("g"), "chicken breast"))
+    );
+    assert_eq!(
+        parse_portion("a cup of rice"),
+        Some(portion(1.0, Some("cup"), "rice"))
+    );
+    assert_eq!(
+        parse_portion("1 1/2 cups milk"),
+        Some(portion(1.5, Some("cups"), "milk"))
+    );
+    assert_eq!(
+        parse_portion("half a glass of milk"),
+        Some(portion(0.5, Some("glass"), "milk"))
+    );
+    assert_eq!(
+        parse_portion("a dozen eggs"),
+        Some(portion(12.0, None, "eggs"))
+    );
+    assert_eq!(parse_portion("apple"), Some(portion(1.0, None, "apple")));
+    assert_eq!(parse_portion("200 g"), None);
+}
+
+#[test]
+fn estimates_weights() {
+    assert_eq!(portion(2.0, None, "eggs").grams(), 100.0);
+    assert_eq!(portion(1.0, Some("cup"), "rice").grams(), 240.0);
+    assert_eq!(portion(0.5, Some("kg"), "chicken").grams(), 500.0);
+    assert_eq!(portion(1.0, None, "stew").grams(), 100.0);
+}
+
+#[test]
+fn matches_foods_in_the_library() {
+    let library = library();
+    let parsed = parse_foods(
+        "2 eggs, 150g chicken breast and a cup of rice",
+        &library,
+        &SkimMatcherV2::default(),
+    );
+
+    assert!(parsed.unmatched.is_empty());
+    let names: Vec<&str> = parsed.foods.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "Egg, whole, raw",
+            "Chicken breast, raw",
+            "Rice, white, cooked"
+        ]
+    );
+
+    let eggs = &parsed.foods[0];
+    assert_eq!((eggs.quantity, eggs.unit.as_str()), (2.0, "piece"));
+    assert_eq!(eggs.calories, 143.0);
+    let chicken = &parsed.foods[1];
+    assert_eq!((chicken.quantity, chicken.unit.as_str()), (150.0, "g"));
+    assert_eq!(chicken.calories, 180.0);
+    assert_eq!(chicken.protein, 33.8);
+    assert_eq!(parsed.foods[2].calories, 312.0);
+}
+
+#[test]
+fn reports_foods_not_in_the_library() {
+    let parsed = parse_foods(
+        "a glass of milk and 3 quinoa bars",
+        &library(),
+        &SkimMatcherV2::default(),
+    );
+    assert_eq!(parsed.foods.len(), 1);
+    assert_eq!(parsed.foods[0].name, "Milk, whole");
+    assert_eq!(parsed.unmatched, vec!["3 quinoa bars"]);
+}
+
+#[test]
+fn near_misses_are_reported_as_unmatched() {
+    let parsed = parse_foods(
+        "a cup of tea and 200g steak",
+        &library(),
+        &SkimMatcherV2::default(),
+    );
+    assert_eq!(parsed.foods.len(), 1);
+    assert_eq!(parsed.foods[0].name, "Beef, steak");
+    assert_eq!(parsed.unmatched, vec!["a cup of tea"]);
+}
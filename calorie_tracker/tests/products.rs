@@ -0,0 +1,182 @@
+use calorie_tracker::import::open_food_facts::{import, Filter};
+use calorie_tracker::products::{is_valid_barcode, Product, ProductStore};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use std::path::{Path, PathBuf};
+
+fn fixture(name: &str) -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/open_food_facts")
+        .join(name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn read(name: &str, filter: &Filter) -> Vec<Product> {
+    let mut products = Vec::new();
+    import(&fixture(name), filter, |product| {
+        products.push(product);
+        Ok(())
+    })
+    .unwrap();
+    products
+}
+
+/// A product store in a temporary directory, removed when dropped.
+struct StoreDir {
+    dir: PathBuf,
+}
+
+impl StoreDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "calorie_tracker-products-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        Self { dir }
+    }
+
+    fn store(&self) -> ProductStore {
+        ProductStore::new(&self.dir.to_string_lossy())
+    }
+}
+
+impl Drop for StoreDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn checks_barcode_check_digits() {
+    for valid in [
+        "96385074",
+        "036000291452",
+        "012345678905",
+        "5201054017906",
+        "00012345678905",
+    ] {
+        assert!(is_valid_barcode(valid), "{}", valid);
+    }
+    for invalid in [
+        "96385075",
+        "036000291453",
+        "5201054017907",
+        "520105401790",
+        "52010540179O6",
+        "",
+    ] {
+        assert!(!is_valid_barcode(invalid), "{}", invalid);
+    }
+}
+
+#[test]
+fn imports_jsonl_dumps() {
+    let products = read("products.jsonl", &Filter::default());
+
+    // Products without a name or energy value are skipped.
+    assert_eq!(
+        products,
+        vec![
+            Product {
+                barcode: "5201054017906".to_string(),
+                name: "Total 5% Greek yoghurt".to_string(),
+                brand: Some("Fage".to_string()),
+                protein: 9.0,
+                fat: 5.0,
+                carbs: 3.0,
+                calories: 93.0,
+                serving_size: Some("170 g".to_string()),
+                serving_grams: Some(170.0),
+            },
+            Product {
+                barcode: "012345678905".to_string(),
+                name: "Oat cookies".to_string(),
+                brand: Some("Good Bakery".to_string()),
+                protein: 6.7,
+                fat: 16.7,
+                carbs: 0.0,
+                // Converted from 1883 kJ.
+                calories: 450.0,
+                serving_size: None,
+                serving_grams: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn imports_csv_dumps() {
+    let products = read("products.csv", &Filter::default());
+
+    assert_eq!(products.len(), 2);
+    assert_eq!(products[0].display_name(), "Dark chocolate (Ritter Sport)");
+    assert_eq!(products[0].calories, 537.0);
+    assert_eq!(products[0].carbs, 47.0);
+    assert_eq!(products[0].serving_grams, Some(25.0));
+    assert_eq!(products[1].barcode, "3017620422003");
+}
+
+#[test]
+fn imports_only_products_of_the_filtered_countries() {
+    let filter = Filter {
+        countries: vec!["United Kingdom".to_string(), "en:austria".to_string()],
+    };
+    let names = |name| {
+        read(name, &filter)
+            .into_iter()
+            .map(|product| product.name)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names("products.jsonl"), vec!["Total 5% Greek yoghurt"]);
+    assert_eq!(names("products.csv"), vec!["Dark chocolate"]);
+}
+
+#[test]
+fn finds_stored_products_by_barcode_and_name() {
+    let dir = StoreDir::new("find");
+    let store = dir.store();
+    let mut writer = store.writer().unwrap();
+    for product in read("products.jsonl", &Filter::default())
+        .iter()
+        .chain(&read("products.csv", &Filter::default()))
+    {
+        writer.add(product).unwrap();
+    }
+    assert_eq!(writer.finish().unwrap(), 4);
+    assert_eq!(store.len().unwrap(), 4);
+
+    let found = store.find_barcode("3017620422003").unwrap().unwrap();
+    assert_eq!(found.name, "Hazelnut spread");
+    // UPC-A and EAN-13 codes of a product differ only in a leading zero.
+    let found = store.find_barcode("0012345678905").unwrap().unwrap();
+    assert_eq!(found.name, "Oat cookies");
+    assert!(store.find_barcode("4008400401621").unwrap().is_none());
+
+    let matcher = SkimMatcherV2::default();
+    let results = store.search(&matcher, "choc ritter", 10).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0.name, "Dark chocolate");
+    assert!(store.search(&matcher, "tea", 10).unwrap().is_empty());
+}
+
+#[test]
+fn inserted_products_replace_those_with_the_same_barcode() {
+    let dir = StoreDir::new("replace");
+    let store = dir.store();
+    let mut writer = store.writer().unwrap();
+    for product in read("products.csv", &Filter::default()) {
+        writer.add(&product).unwrap();
+    }
+    writer.finish().unwrap();
+
+    let mut spread = store.find_barcode("3017620422003").unwrap().unwrap();
+    spread.calories = 540.0;
+    store.insert(spread).unwrap();
+
+    assert_eq!(store.len().unwrap(), 2);
+    let found = store.find_barcode("3017620422003").unwrap().unwrap();
+    assert_eq!(found.calories, 540.0);
+    assert!(store.find_barcode("4000417025005").unwrap().is_some());
+}
@@ -0,0 +1,513 @@
+use calorie_tracker::day::Day;
+use calorie_tracker::storage::{Layout, LockMode, Store};
+use calorie_tracker::{App, Food, Workout, WorkoutType};
+use chrono::{Local, NaiveDate};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// A data file in a temporary directory of its own.
+struct DataDir {
+    dir: PathBuf,
+}
+
+impl DataDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "calorie_tracker-storage-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self { dir }
+    }
+
+    fn path(&self) -> String {
+        self.dir
+            .join("calories.json")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn open(&self) -> App {
+        App::new(&self.path()).unwrap()
+    }
+}
+
+impl Drop for DataDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn egg() -> Food {
+    Food::new("egg", 1.0, "piece", 6.3, 4.8, 0.4, 72.0)
+}
+
+#[test]
+fn instances_see_each_others_changes() {
+    let data = DataDir::new("instances");
+    let mut first = data.open();
+    let mut second = data.open();
+
+    first.add_food(egg(), 1.0).unwrap();
+    second
+        .add_workout(Workout::new(WorkoutType::WeightLifting, 30))
+        .unwrap();
+
+    // The second instance picked up the egg before saving its workout.
+    let day = second.get_current_day().unwrap();
+    assert_eq!(day.foods.len(), 1);
+    assert_eq!(day.workouts.len(), 1);
+
+    let day = data.open().get_current_day().unwrap().clone();
+    assert_eq!(day.foods[0].name, "egg");
+    assert_eq!(day.workouts[0].calories_burnt, 120);
+}
+
+#[test]
+fn foods_in_other_units_are_kept_apart() {
+    let data = DataDir::new("units");
+    let mut app = data.open();
+    let grams = Food::new("egg", 100.0, "g", 12.6, 9.5, 0.7, 143.0);
+
+    app.add_food(egg(), 2.0).unwrap();
+    app.add_food(grams.clone(), 100.0).unwrap();
+    app.add_food(egg(), 1.0).unwrap();
+
+    let foods = data.open().get_current_day().unwrap().foods.clone();
+    assert_eq!(foods.len(), 2);
+    assert_eq!((foods[0].quantity, foods[0].unit.as_str()), (3.0, "piece"));
+    assert_eq!((foods[1].quantity, foods[1].unit.as_str()), (100.0, "g"));
+
+    // Merging the days of a legacy file keeps them apart as well.
+    let mut day = Day::new(Local::now().date_naive());
+    day.add_food(egg(), 2.0);
+    day.add_food(grams, 100.0);
+    assert_eq!(day.foods.len(), 2);
+}
+
+#[test]
+fn moving_between_days_records_nothing() {
+    let data = DataDir::new("navigation");
+    let mut app = data.open();
+    let journal = format!("{}.journal", data.path());
+    let size = || std::fs::metadata(&journal).map_or(0, |metadata| metadata.len());
+    let before = size();
+
+    for _ in 0..3 {
+        app.previous_day().unwrap();
+    }
+    app.next_day().unwrap();
+    assert_eq!(size(), before);
+
+    let logged = app.get_current_day().unwrap().date;
+    app.add_food(egg(), 1.0).unwrap();
+    app.go_to_today().unwrap();
+    let today = Local::now().date_naive();
+    let dates: Vec<NaiveDate> = data
+        .open()
+        .days_in_range(today - chrono::Duration::days(7), today)
+        .map(|day| day.date)
+        .collect();
+    // Today is open, but only the day with the egg was saved.
+    assert_eq!(dates, vec![logged, today]);
+}
+
+#[test]
+fn changes_wait_for_the_lock() {
+    let data = DataDir::new("lock");
+    let mut app = data.open();
+    let lock = Store::new(&data.path()).lock(LockMode::Exclusive).unwrap();
+
+    let (done, finished) = mpsc::channel();
+    let writer = thread::spawn(move || {
+        app.add_food(egg(), 1.0).unwrap();
+        done.send(()).unwrap();
+    });
+    assert!(finished.recv_timeout(Duration::from_millis(300)).is_err());
+
+    drop(lock);
+    finished.recv_timeout(Duration::from_secs(5)).unwrap();
+    writer.join().unwrap();
+    assert_eq!(data.open().get_current_day().unwrap().foods.len(), 1);
+}
+
+#[test]
+fn undo_history_survives_restarts() {
+    let data = DataDir::new("undo");
+    let mut app = data.open();
+    app.add_food(egg(), 1.0).unwrap();
+    app.add_workout(Workout::new(WorkoutType::Cardio, 20))
+        .unwrap();
+    drop(app);
+
+    let mut app = data.open();
+    assert_eq!(app.undo().unwrap().as_deref(), Some("Add Cardio"));
+    drop(app);
+
+    let mut app = data.open();
+    let day = app.get_current_day().unwrap();
+    assert_eq!((day.foods.len(), day.workouts.len()), (1, 0));
+    assert_eq!(app.next_redo(), Some("Add Cardio"));
+    assert_eq!(app.redo().unwrap().as_deref(), Some("Add Cardio"));
+    assert_eq!(data.open().get_current_day().unwrap().workouts.len(), 1);
+}
+
+#[test]
+fn failed_undo_keeps_the_action() {
+    let data = DataDir::new("failed-undo");
+    let mut app = data.open();
+    app.add_food(egg(), 1.0).unwrap();
+    app.add_food(Food::new("toast", 1.0, "slice", 3.0, 1.0, 14.0, 80.0), 1.0)
+        .unwrap();
+    drop(app);
+
+    // Restoring the day before the toast would save an entry without a name.
+    let history = format!("{}.history", data.path());
+    let contents = std::fs::read_to_string(&history).unwrap();
+    std::fs::write(&history, contents.replace("\"egg\"", "\"\"")).unwrap();
+
+    let mut app = data.open();
+    assert!(app.undo().is_err());
+    assert_eq!(app.next_undo(), Some("Add toast"));
+    assert_eq!(app.get_current_day().unwrap().foods.len(), 2);
+}
+
+fn toast() -> Food {
+    Food::new("toast", 1.0, "slice", 3.0, 1.0, 14.0, 80.0)
+}
+
+#[test]
+fn changes_follow_entries_other_instances_moved() {
+    let data = DataDir::new("moved");
+    let mut first = data.open();
+    first.add_food(egg(), 1.0).unwrap();
+    first.add_food(toast(), 1.0).unwrap();
+    let mut second = data.open();
+
+    second.remove_food(0).unwrap();
+    // The first instance still sees the toast as entry #2.
+    first.remove_food(1).unwrap();
+    assert!(data.open().get_current_day().unwrap().foods.is_empty());
+}
+
+#[test]
+fn changes_to_entries_other_instances_removed_are_refused() {
+    let data = DataDir::new("conflict");
+    let mut first = data.open();
+    first.add_food(egg(), 1.0).unwrap();
+    first.add_food(toast(), 1.0).unwrap();
+    let mut second = data.open();
+
+    second.remove_food(0).unwrap();
+    assert!(first.remove_food(0).is_err());
+    let foods = data.open().get_current_day().unwrap().foods.clone();
+    assert_eq!(foods.len(), 1);
+    assert_eq!(foods[0].name, "toast");
+}
+
+#[test]
+fn edits_workouts() {
+    let data = DataDir::new("edit-workout");
+    let mut app = data.open();
+    app.add_workout(Workout::new(WorkoutType::WeightLifting, 30))
+        .unwrap();
+    let date = app.get_current_day().unwrap().date;
+    let mut run = Workout::new(WorkoutType::Cardio, 45);
+    run.name = Some("running".to_string());
+    run.calories_burnt = 400;
+
+    app.edit_workout(date, 0, run.clone()).unwrap();
+    assert_eq!(data.open().get_current_day().unwrap().workouts, vec![run]);
+    assert!(app
+        .edit_workout(date, 1, Workout::new(WorkoutType::Cardio, 10))
+        .is_err());
+
+    app.undo().unwrap();
+    let workouts = data.open().get_current_day().unwrap().workouts.clone();
+    assert_eq!(workouts[0].workout_type, WorkoutType::WeightLifting);
+}
+
+#[test]
+fn undo_takes_back_the_latest_change_of_any_instance() {
+    let data = DataDir::new("shared-undo");
+    let mut first = data.open();
+    let mut second = data.open();
+    first.add_food(egg(), 1.0).unwrap();
+    second.add_food(toast(), 1.0).unwrap();
+
+    // The history is shared, so the toast is undone first and the egg kept.
+    assert_eq!(first.undo().unwrap().as_deref(), Some("Add toast"));
+    let foods = data.open().get_current_day().unwrap().foods.clone();
+    assert_eq!(foods.len(), 1);
+    assert_eq!(foods[0].name, "egg");
+    assert_eq!(second.redo().unwrap().as_deref(), Some("Add toast"));
+    assert_eq!(data.open().get_current_day().unwrap().foods.len(), 2);
+}
+
+#[test]
+fn undo_of_days_other_instances_changed_is_refused() {
+    let data = DataDir::new("undo-conflict");
+    let mut first = data.open();
+    first.add_food(egg(), 1.0).unwrap();
+    let history = format!("{}.history", data.path());
+    let recorded = std::fs::read(&history).unwrap();
+
+    // The toast is saved, but its action is lost from the history, as an
+    // older version that did not share it would do.
+    let mut second = data.open();
+    second.add_food(toast(), 1.0).unwrap();
+    std::fs::write(&history, recorded).unwrap();
+
+    let error = first.undo().unwrap_err();
+    assert!(error.to_string().contains("another instance"), "{}", error);
+    assert_eq!(first.next_undo(), Some("Add egg"));
+    assert_eq!(data.open().get_current_day().unwrap().foods.len(), 2);
+}
+
+#[test]
+fn loads_legacy_files_by_date() {
+    let data = DataDir::new("legacy");
+    let food = |name: &str, calories: f64| {
+        serde_json::json!({
+            "name": name, "quantity": 1.0, "unit": "piece",
+            "protein": 1.0, "fat": 1.0, "carbs": 1.0, "calories": calories,
+        })
+    };
+    // Days were kept in a list, not always in order, and a date could appear
+    // twice; workouts were a single, possibly null, field.
+    let legacy = serde_json::json!([
+        { "date": "2024-03-02", "foods": [food("toast", 80.0)], "workout": null },
+        {
+            "date": "2024-03-01",
+            "foods": [food("egg", 72.0)],
+            "workout": { "workout_type": "Cardio", "duration": 20, "calories_burnt": 200 },
+        },
+        { "date": "2024-03-02", "foods": [food("toast", 80.0), food("jam", 50.0)] },
+    ]);
+    std::fs::write(data.path(), legacy.to_string()).unwrap();
+
+    for app in [data.open(), data.open()] {
+        let start = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let days: Vec<_> = app
+            .days_in_range(start, start + chrono::Duration::days(1))
+            .cloned()
+            .collect();
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].date, start);
+        assert_eq!(days[0].workouts[0].calories_burnt, 200);
+        let toast = &days[1].foods[0];
+        assert_eq!((toast.quantity, toast.calories), (2.0, 160.0));
+        assert_eq!(days[1].foods[1].name, "jam");
+        assert!(days[1].workouts.is_empty());
+    }
+}
+
+#[test]
+fn replays_the_journal_after_the_snapshot() {
+    let data = DataDir::new("journal");
+    let mut app = data.open();
+    for _ in 0..105 {
+        app.add_workout(Workout::new(WorkoutType::WeightLifting, 30))
+            .unwrap();
+    }
+    drop(app);
+
+    // A snapshot was written after 100 events; the rest are replayed.
+    let snapshot: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(data.path()).unwrap()).unwrap();
+    assert_eq!(snapshot["seq"], 100);
+    let journal = std::fs::metadata(format!("{}.journal", data.path())).unwrap();
+    assert!(snapshot["offset"].as_u64().unwrap() < journal.len());
+
+    let app = data.open();
+    assert_eq!(app.get_current_day().unwrap().workouts.len(), 105);
+    let events = app.events().unwrap();
+    assert_eq!(events.len(), 105);
+    let days = app.days_at(events[9].at).unwrap();
+    let day = days.values().next().unwrap();
+    assert!(day.workouts.len() >= 10 && day.workouts.len() < 105);
+}
+
+#[test]
+fn repairs_invalid_data() {
+    let data = DataDir::new("repair");
+    let day = |date: &str, foods: serde_json::Value| serde_json::json!({ "date": date, "foods": foods, "workout": null });
+    let food = |name: &str, calories: f64| {
+        serde_json::json!({
+            "name": name, "quantity": 1.0, "unit": "piece",
+            "protein": 1.0, "fat": 1.0, "carbs": 1.0, "calories": calories,
+        })
+    };
+    let days = serde_json::json!([
+        day("2024-01-02", serde_json::json!([food("egg", 72.0)])),
+        day(
+            "2024-01-01",
+            serde_json::json!([food("", 10.0), food("jam", -5.0)])
+        ),
+        day("2024-01-02", serde_json::json!([food("toast", 80.0)])),
+    ]);
+    std::fs::write(data.path(), days.to_string()).unwrap();
+
+    let mut app = data.open();
+    let problems: Vec<String> = app.check().problems.iter().map(|p| p.to_string()).collect();
+    assert_eq!(
+        problems,
+        vec![
+            "2024-01-01 is stored after the later date 2024-01-02",
+            "2024-01-02 is recorded more than once",
+            "2024-01-01: entry #1 has no name",
+            "2024-01-01: entry #2 (jam) has invalid calories -5",
+        ]
+    );
+    assert_eq!(app.repair().unwrap().problems.len(), 4);
+
+    let mut app = data.open();
+    assert!(app.check().is_ok());
+    let date = |s: &str| s.parse().unwrap();
+    let first = app.days_in_range(date("2024-01-01"), date("2024-01-01"));
+    assert!(first.map(|day| day.foods.len()).eq([0]));
+    app.change_day(date("2024-01-02")).unwrap();
+    let names: Vec<String> = app
+        .get_current_day()
+        .unwrap()
+        .foods
+        .iter()
+        .map(|food| food.name.clone())
+        .collect();
+    assert_eq!(names, vec!["egg", "toast"]);
+}
+
+#[test]
+fn encrypts_and_decrypts_the_data() {
+    let data = DataDir::new("encrypt");
+    let mut app = data.open();
+    app.add_food(egg(), 1.0).unwrap();
+    app.set_passphrase(Some("first secret")).unwrap();
+    app.add_food(toast(), 1.0).unwrap();
+    drop(app);
+
+    let contents = std::fs::read(data.path()).unwrap();
+    assert!(!String::from_utf8_lossy(&contents).contains("egg"));
+    assert!(App::new(&data.path()).is_err());
+    assert!(App::open(&data.path(), Some("wrong")).is_err());
+
+    let mut app = App::open(&data.path(), Some("first secret")).unwrap();
+    assert_eq!(app.get_current_day().unwrap().foods.len(), 2);
+    app.set_passphrase(Some("second secret")).unwrap();
+    drop(app);
+    assert!(App::open(&data.path(), Some("first secret")).is_err());
+
+    let mut app = App::open(&data.path(), Some("second secret")).unwrap();
+    assert_eq!(app.undo().unwrap().as_deref(), Some("Add toast"));
+    app.set_passphrase(None).unwrap();
+    drop(app);
+    assert_eq!(data.open().get_current_day().unwrap().foods.len(), 1);
+    assert!(!std::path::Path::new(&format!("{}.staging", data.path())).exists());
+}
+
+#[test]
+fn refused_encryption_leaves_the_files_alone() {
+    let data = DataDir::new("encrypt-invalid");
+    let invalid = serde_json::json!([{
+        "date": "2024-01-01",
+        "foods": [{
+            "name": "", "quantity": 1.0, "unit": "piece",
+            "protein": 1.0, "fat": 1.0, "carbs": 1.0, "calories": 10.0,
+        }],
+        "workout": null,
+    }]);
+    std::fs::write(data.path(), invalid.to_string()).unwrap();
+
+    let mut app = data.open();
+    assert!(app.set_passphrase(Some("secret")).is_err());
+    drop(app);
+    let mut files: Vec<String> = std::fs::read_dir(&data.dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        vec![
+            "calories.json",
+            "calories.json.journal",
+            "calories.json.lock"
+        ]
+    );
+    assert!(!data.open().is_encrypted());
+}
+
+#[test]
+fn converts_to_one_file_per_day() {
+    let data = DataDir::new("per-day");
+    let mut app = data.open();
+    app.add_food(egg(), 1.0).unwrap();
+    app.set_user_info(170.0, 65.0, 40, calorie_tracker::Gender::Female)
+        .unwrap();
+
+    let dir = data.dir.join("days");
+    let dir_path = dir.to_string_lossy().into_owned();
+    app.convert(&dir_path, Layout::PerDay).unwrap();
+    app.add_food(toast(), 1.0).unwrap();
+    drop(app);
+
+    let date = chrono::Local::now().date_naive();
+    let day_file = dir.join(date.format("%Y/%m/%d.json").to_string());
+    let day: serde_json::Value = serde_json::from_slice(&std::fs::read(day_file).unwrap()).unwrap();
+    assert_eq!(day["foods"][1]["name"], "toast");
+
+    let app = App::new(&dir_path).unwrap();
+    assert_eq!(app.layout(), Layout::PerDay);
+    assert_eq!(app.get_current_day().unwrap().foods.len(), 2);
+    assert_eq!(app.profile().age, 40);
+    // The original data file is left as it was.
+    assert_eq!(data.open().get_current_day().unwrap().foods.len(), 1);
+}
+
+#[test]
+fn archives_and_restores_old_days() {
+    let data = DataDir::new("archive");
+    let date = |s: &str| s.parse::<chrono::NaiveDate>().unwrap();
+    let mut app = data.open();
+    for day in ["2020-03-01", "2020-03-02", "2021-01-01"] {
+        app.change_day(date(day)).unwrap();
+        app.add_food(egg(), 1.0).unwrap();
+    }
+    assert_eq!(
+        app.archive(date("2020-01-01"), date("2020-12-31")).unwrap(),
+        2
+    );
+    drop(app);
+
+    assert!(std::path::Path::new(&format!("{}.archive/2020.json.gz", data.path())).exists());
+    let snapshot = std::fs::read_to_string(data.path()).unwrap();
+    assert!(!snapshot.contains("2020-03-01") && snapshot.contains("2021-01-01"));
+
+    let mut app = data.open();
+    let in_2020 = |app: &App| {
+        app.days_in_range(date("2020-01-01"), date("2020-12-31"))
+            .count()
+    };
+    assert_eq!(in_2020(&app), 0);
+    app.load_range(date("2020-03-01"), date("2020-03-01"))
+        .unwrap();
+    assert_eq!(in_2020(&app), 2);
+
+    // Archived days can still be changed.
+    app.change_day(date("2020-03-02")).unwrap();
+    app.add_food(toast(), 1.0).unwrap();
+    assert_eq!(
+        app.unarchive(date("2020-01-01"), date("2020-12-31"))
+            .unwrap(),
+        2
+    );
+    drop(app);
+
+    assert!(data.open().archived_years().is_empty());
+    let snapshot = std::fs::read_to_string(data.path()).unwrap();
+    assert!(snapshot.contains("2020-03-01") && snapshot.contains("toast"));
+}
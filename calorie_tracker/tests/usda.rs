@@ -0,0 +1,108 @@
+#[allow(dead_code)]
+mod common;
+
+use calorie_tracker::api::{ApiError, HttpClient, NutritionInfo, NutritionProvider, Usda};
+use calorie_tracker::config::HttpSettings;
+use calorie_tracker::import::usda;
+use common::{MockServer, Reply};
+
+fn usda(server: &MockServer) -> Usda {
+    let client = HttpClient::new(&HttpSettings {
+        retries: 0,
+        ..HttpSettings::default()
+    })
+    .unwrap();
+    Usda::new("test-key".to_string(), client).with_base_url(server.url())
+}
+
+#[test]
+fn carries_serving_sizes_of_branded_foods() {
+    let path =
+        std::env::temp_dir().join(format!("calorie_tracker-usda-{}.json", std::process::id()));
+    let nutrient =
+        |id: u32, amount: f64| serde_json::json!({ "nutrient": { "id": id }, "amount": amount });
+    let dump = serde_json::json!({
+        "BrandedFoods": [{
+            "fdcId": 1,
+            "description": "Oat cookies",
+            "gtinUpc": "012345678905",
+            "servingSize": 30.0,
+            "servingSizeUnit": "g",
+            "householdServingFullText": "2 cookies",
+            "foodNutrients": [nutrient(1008, 450.0), nutrient(1003, 6.0)],
+        }],
+        "FoundationFoods": [{
+            "fdcId": 2,
+            "description": "Oats",
+            "foodNutrients": [nutrient(1008, 380.0)],
+        }],
+    });
+    std::fs::write(&path, dump.to_string()).unwrap();
+    let foods = usda::import(&path.to_string_lossy()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let product = foods[0].to_product("012345678905");
+    assert_eq!(product.serving_size.as_deref(), Some("2 cookies (30 g)"));
+    assert_eq!(product.serving_grams, Some(30.0));
+    assert_eq!(product.per_serving().unwrap().calories, 135.0);
+    assert_eq!(foods[1].serving_grams, None);
+}
+
+#[tokio::test]
+async fn searches_foods_per_100_grams() {
+    let server = MockServer::start(vec![Reply::fixture(200, "usda/search.json")]);
+    let foods = usda(&server).search("cooked rice").await.unwrap();
+
+    assert_eq!(
+        foods,
+        vec![NutritionInfo {
+            name: "rice, white, long grain, cooked".to_string(),
+            quantity: 100.0,
+            unit: "g".to_string(),
+            calories: 130.0,
+            protein: 2.69,
+            fat: 0.28,
+            // The result has no carbohydrate value.
+            carbs: 0.0,
+        }]
+    );
+    assert_eq!(
+        server.requests()[0].target,
+        "/v1/foods/search?query=cooked+rice&pageSize=1&api_key=test-key"
+    );
+}
+
+#[tokio::test]
+async fn missing_resources_are_not_found() {
+    let server = MockServer::start(vec![Reply::fixture(404, "usda/not_found.json")]);
+    let error = usda(&server).search("cooked rice").await.unwrap_err();
+    assert!(matches!(error, ApiError::NotFound(_)), "{:?}", error);
+}
+
+#[tokio::test]
+async fn looks_up_barcodes_among_branded_foods() {
+    let server = MockServer::start(vec![Reply::fixture(200, "usda/branded_search.json")]);
+    // The EAN-13 form of the UPC-A code in the results.
+    let product = usda(&server)
+        .lookup_barcode("0012345678905")
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(product.barcode, "0012345678905");
+    assert_eq!(product.display_name(), "OAT COOKIES (Good Bakery Inc.)");
+    assert_eq!(product.calories, 450.0);
+    assert_eq!(product.carbs, 66.7);
+    assert_eq!(product.serving_grams, Some(30.0));
+    assert_eq!(
+        server.requests()[0].target,
+        "/v1/foods/search?query=0012345678905&dataType=Branded&pageSize=5&api_key=test-key"
+    );
+}
+
+#[tokio::test]
+async fn barcodes_missing_from_the_results_are_not_found() {
+    let server = MockServer::start(vec![Reply::fixture(200, "usda/branded_search.json")]);
+    let product = usda(&server).lookup_barcode("5201054017906").await.unwrap();
+    assert!(product.is_none());
+}